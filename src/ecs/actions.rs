@@ -1,11 +1,31 @@
 use crate::ecs::{
+    audio::SoundQueue,
+    checkpoints::RespawnPoint,
+    collectibles::InventoryComponent,
+    health::{
+        DeathBehavior,
+        HealthComponent,
+    },
+    level::FollowEntity,
+    particles::{
+        ParticleBursts,
+        ParticleSettings,
+    },
     physics::{
+        ContactsComponent,
         NextPhysicsStep,
         PhysicsComponent,
     },
     rect::RectComponent,
+    variables::{
+        VariablesComponent,
+        VariableValue,
+    },
+};
+use nalgebra::{
+    Point2,
+    Vector2,
 };
-use nalgebra::Vector2;
 use serde::{
     Deserialize,
     Serialize,
@@ -20,10 +40,80 @@ use specs::{
     },
 };
 use specs_derive::Component;
+use std::path::PathBuf;
 
 #[derive(Component)]
 pub struct ActionComponent {
-    pub actions_remaining: Vec<Actions>
+    pub actions_remaining: Vec<Actions>,
+    /// Actions envoyées par une touche maintenue, les sauts qu'elles lancent sont variables
+    held_input_actions: Vec<Actions>,
+    /// Actions différées et nombre de ticks restants avant leur exécution
+    pending: Vec<(u32, Actions)>,
+}
+
+impl ActionComponent {
+    /// Ajoute l'action à effectuer au prochain passage de l'`ActionSystem`, en créant le composant si nécessaire.
+    pub fn push_action<'a>(actions: &mut WriteStorage<'a, ActionComponent>, entity: Entity, action: Actions) {
+        if let Some(a) = actions.get_mut(entity) {
+            a.actions_remaining.push(action);
+            return;
+        }
+
+        // L'entité a pu être supprimée entre-temps, l'action est alors abandonnée
+        let _ = actions.insert(entity, ActionComponent { actions_remaining: vec![action], held_input_actions: vec![], pending: vec![] });
+    }
+
+    /// Ajoute une action envoyée à chaque tick où la touche est maintenue.
+    pub fn push_held_input_action<'a>(actions: &mut WriteStorage<'a, ActionComponent>, entity: Entity, action: Actions) {
+        if let Some(a) = actions.get_mut(entity) {
+            a.held_input_actions.push(action);
+            return;
+        }
+
+        let _ = actions.insert(entity, ActionComponent { actions_remaining: vec![], held_input_actions: vec![action], pending: vec![] });
+    }
+
+    /// Programme l'action dans `ticks` passages de l'`ActionSystem`.
+    pub fn schedule_action<'a>(actions: &mut WriteStorage<'a, ActionComponent>, entity: Entity, ticks: u32, action: Actions) {
+        if let Some(a) = actions.get_mut(entity) {
+            a.pending.push((ticks, action));
+            return;
+        }
+
+        let _ = actions.insert(entity, ActionComponent { actions_remaining: vec![], held_input_actions: vec![], pending: vec![(ticks, action)] });
+    }
+
+    /// Retire les actions à effectuer pendant ce tick : les actions ajoutées et les actions différées arrivées à échéance.
+    /// Chaque action est accompagnée d'un booléen vrai si elle provient d'une touche maintenue.
+    fn take_due_actions(&mut self) -> Vec<(Actions, bool)> {
+        let mut due: Vec<(Actions, bool)> = self.actions_remaining.drain(..).map(|action| (action, false)).collect();
+        due.extend(self.held_input_actions.drain(..).map(|action| (action, true)));
+
+        for (ticks, _) in self.pending.iter_mut() {
+            *ticks = ticks.saturating_sub(1);
+        }
+
+        let (ready, waiting): (Vec<(u32, Actions)>, Vec<(u32, Actions)>) = self.pending.drain(..).partition(|(ticks, _)| *ticks == 0);
+        self.pending = waiting;
+
+        due.extend(ready.into_iter().map(|(_, action)| (action, false)));
+
+        due
+    }
+}
+
+/// Condition testée par `Actions::If` sur l'entité qui effectue l'action.
+#[derive(Clone, Serialize, Deserialize, Debug)]
+pub enum Condition {
+    OnGround,
+    HealthBelow(u32),
+    HasKey(u32),
+    HasCoins(u32),
+    EntityAlive(Option<U64Marker>),
+    /// Variable de l'entité marquée, ou de l'entité elle-même si aucun marqueur n'est donné
+    VariableEquals(Option<U64Marker>, String, VariableValue),
+    VariableAtLeast(Option<U64Marker>, String, f64),
+    Not(Box<Condition>),
 }
 
 #[derive(Clone, Serialize, Deserialize, Debug)]
@@ -32,72 +122,295 @@ pub enum Actions {
     Move(Vector2<f64>),
     PhysicsMove(Vector2<f64>),
     PhysicsJump(u32),
+    PhysicsImpulse(Vector2<f64>),
+    PhysicsForce(Vector2<f64>),
+    PhysicsDropThrough,
     DeleteEntity,
+    Damage(u32),
+    Heal(u32),
+    Kill,
+    /// Ramène l'entité suivie au dernier point de contrôle activé
+    Respawn,
     EntityAction(Option<U64Marker>, Box<Actions>),
     MultipleActions(Vec<Actions>),
+    /// Effectue l'action après le nombre de ticks donné
+    Delay(u32, Box<Actions>),
+    /// Effectue l'action `count` fois (indéfiniment si 0), avec `interval` ticks entre deux exécutions
+    Repeat(u32, u32, Box<Actions>),
+    If(Condition, Box<Actions>, Box<Actions>),
+    /// Les variables sont celles de l'entité marquée, ou de l'entité elle-même si aucun marqueur n'est donné
+    SetVariable(Option<U64Marker>, String, VariableValue),
+    IncrementVariable(Option<U64Marker>, String, i64),
+    ToggleVariable(Option<U64Marker>, String),
+    /// Émet le nombre de particules donné depuis le centre de l'entité
+    SpawnParticles(ParticleSettings, u32),
+    /// Joue le son au volume donné, atténué selon la distance entre l'entité et la caméra
+    PlaySound(PathBuf, f32),
 }
 
-pub struct ActionSystem;
+impl Default for Actions {
+    fn default() -> Self { Actions::Empty }
+}
 
-impl<'a> System<'a> for ActionSystem {
-    type SystemData = (
-        Entities<'a>,
-        Read<'a, U64MarkerAllocator>,
-        WriteStorage<'a, RectComponent>,
-        WriteStorage<'a, ActionComponent>,
-        WriteStorage<'a, PhysicsComponent>,
-    );
+/// Ressources et stockages modifiés par les actions.
+struct ActionData<'r, 'a: 'r> {
+    entities: &'r Entities<'a>,
+    u64_marker_allocator: &'r U64MarkerAllocator,
+    follow_entity: &'r FollowEntity,
+    respawn: &'r RespawnPoint,
+    rects: &'r mut WriteStorage<'a, RectComponent>,
+    physics: &'r mut WriteStorage<'a, PhysicsComponent>,
+    healths: &'r mut WriteStorage<'a, HealthComponent>,
+    contacts: &'r ReadStorage<'a, ContactsComponent>,
+    inventories: &'r ReadStorage<'a, InventoryComponent>,
+    variables: &'r mut WriteStorage<'a, VariablesComponent>,
+    particle_bursts: &'r mut ParticleBursts,
+    sounds: &'r mut SoundQueue,
+    /// Actions différées ajoutées pendant ce tick
+    scheduled: Vec<(Entity, u32, Actions)>,
+    /// Vrai pendant l'exécution d'une action envoyée par une touche maintenue
+    held_input: bool,
+}
 
-    fn run(&mut self, (entities, u64_marker_allocator, mut rects, mut actions, mut physics): Self::SystemData) {
-        fn perform_action<'a>(
-            entity: Entity,
-            action: &Actions,
-            entities: &Entities<'a>,
-            u64_marker_allocator: &U64MarkerAllocator,
-            rect_storage: &mut WriteStorage<'a, RectComponent>,
-            phys_storage: &mut WriteStorage<'a, PhysicsComponent>) {
-            match action {
-                Actions::Move(mv) => {
-                    if let Some(rect) = rect_storage.get_mut(entity) {
-                        rect.pos_mut().x += mv.x;
-                        rect.pos_mut().y += mv.y;
-                    }
+impl<'r, 'a> ActionData<'r, 'a> {
+    fn get_followed_entity(&self) -> Option<Entity> {
+        self.follow_entity.0.and_then(|m| self.u64_marker_allocator.retrieve_entity_internal(m.id()))
+    }
+
+    /// Entité dont les variables sont lues ou modifiées.
+    fn get_variables_entity(&self, entity: Entity, marker: &Option<U64Marker>) -> Option<Entity> {
+        match marker {
+            Some(marker) => self.u64_marker_allocator.retrieve_entity_internal(marker.id()),
+            None => Some(entity)
+        }
+    }
+
+    fn get_variable(&self, entity: Entity, marker: &Option<U64Marker>, name: &str) -> Option<VariableValue> {
+        self.get_variables_entity(entity, marker).and_then(|e| self.variables.get(e)).and_then(|v| v.get(name).cloned())
+    }
+
+    /// Modifie les variables de l'entité, le composant est créé si nécessaire.
+    fn update_variables<F: FnOnce(&mut VariablesComponent)>(&mut self, entity: Entity, marker: &Option<U64Marker>, update: F) {
+        // Un marqueur périmé peut désigner une entité supprimée, ses variables sont alors ignorées
+        if let Some(target) = self.get_variables_entity(entity, marker).filter(|t| self.entities.is_alive(*t)) {
+            if !self.variables.contains(target) && self.variables.insert(target, VariablesComponent::default()).is_err() {
+                return;
+            }
+
+            if let Some(variables) = self.variables.get_mut(target) {
+                update(variables);
+            }
+        }
+    }
+
+    fn check_condition(&self, entity: Entity, condition: &Condition) -> bool {
+        match condition {
+            Condition::OnGround => self.contacts.get(entity).map_or(false, |c| c.is_grounded()),
+            Condition::HealthBelow(hp) => self.healths.get(entity).map_or(false, |h| h.current < *hp),
+            Condition::HasKey(key) => self.inventories.get(entity).map_or(false, |i| i.has_key(*key)),
+            Condition::HasCoins(coins) => self.inventories.get(entity).map_or(false, |i| i.coins >= *coins),
+            Condition::EntityAlive(marker) => marker
+                .and_then(|m| self.u64_marker_allocator.retrieve_entity_internal(m.id()))
+                .map_or(false, |e| self.entities.is_alive(e)),
+            Condition::VariableEquals(marker, name, value) => self.get_variable(entity, marker, name).map_or(false, |v| v == *value),
+            Condition::VariableAtLeast(marker, name, min) => self.get_variable(entity, marker, name).and_then(|v| v.as_f64()).map_or(false, |v| v >= *min),
+            Condition::Not(condition) => !self.check_condition(entity, condition),
+        }
+    }
+
+    fn perform_action(&mut self, entity: Entity, action: &Actions) {
+        match action {
+            Actions::Move(mv) => {
+                if let Some(rect) = self.rects.get_mut(entity) {
+                    rect.pos_mut().x += mv.x;
+                    rect.pos_mut().y += mv.y;
                 }
-                Actions::PhysicsMove(mv) => {
-                    if let Some(phys) = phys_storage.get_mut(entity) {
-                        phys.add_step(NextPhysicsStep::Move(*mv))
-                    }
+            }
+            Actions::PhysicsMove(mv) => {
+                if let Some(phys) = self.physics.get_mut(entity) {
+                    phys.add_step(NextPhysicsStep::Move(*mv))
                 }
-                Actions::PhysicsJump(height) => {
-                    if let Some(phys) = phys_storage.get_mut(entity) {
-                        phys.add_step(NextPhysicsStep::Jump(*height))
-                    }
+            }
+            Actions::PhysicsJump(height) => {
+                if let Some(phys) = self.physics.get_mut(entity) {
+                    phys.add_step(NextPhysicsStep::Jump(*height, self.held_input))
+                }
+            }
+            Actions::PhysicsImpulse(impulse) => {
+                if let Some(phys) = self.physics.get_mut(entity) {
+                    phys.add_step(NextPhysicsStep::Impulse(*impulse))
                 }
-                Actions::DeleteEntity => {
-                    entities.delete(entity).unwrap();
+            }
+            Actions::PhysicsForce(force) => {
+                if let Some(phys) = self.physics.get_mut(entity) {
+                    phys.add_step(NextPhysicsStep::Force(*force))
+                }
+            }
+            Actions::PhysicsDropThrough => {
+                if let Some(phys) = self.physics.get_mut(entity) {
+                    phys.add_step(NextPhysicsStep::DropThrough)
                 }
-                Actions::EntityAction(u64_marker, action) => {
-                    if let Some(u64_marker) = u64_marker {
-                        if let Some(ent) = u64_marker_allocator.retrieve_entity_internal(u64_marker.id()) {
-                            perform_action(ent, action, entities, u64_marker_allocator, rect_storage, phys_storage);
+            }
+            Actions::DeleteEntity => {
+                // La cible a pu être supprimée plus tôt, par une autre action ou derrière un marqueur périmé
+                if self.entities.is_alive(entity) {
+                    self.entities.delete(entity).unwrap();
+                }
+            }
+            Actions::Damage(_) | Actions::Heal(_) | Actions::Kill => {
+                let death = match self.healths.get_mut(entity) {
+                    Some(health) => match action {
+                        Actions::Damage(amount) => health.damage(*amount),
+                        Actions::Heal(amount) => {
+                            health.heal(*amount);
+                            false
                         }
+                        _ => health.kill()
+                    },
+                    None => false
+                };
+
+                if death {
+                    if let Some(death_action) = self.healths.get(entity).map(|h| h.death_action.clone()) {
+                        self.perform_action(entity, &death_action);
                     }
+
+                    let respawn = self.healths.get(entity).map_or(false, |h| h.on_death == DeathBehavior::Respawn) && self.get_followed_entity() == Some(entity);
+
+                    self.perform_action(entity, if respawn { &Actions::Respawn } else { &Actions::DeleteEntity });
                 }
-                Actions::MultipleActions(actions) => {
-                    for action in actions.iter() {
-                        perform_action(entity, action, entities, u64_marker_allocator, rect_storage, phys_storage);
+            }
+            Actions::Respawn => {
+                if let (Some(followed), Some(pos)) = (self.get_followed_entity(), self.respawn.pos) {
+                    if let Some(rect) = self.rects.get_mut(followed) {
+                        rect.move_to(pos);
                     }
+
+                    if let Some(phys) = self.physics.get_mut(followed) {
+                        if let Some(ref saved) = self.respawn.physics {
+                            *phys = saved.clone();
+                        }
+
+                        phys.reset_motion();
+                    }
+
+                    if let Some(health) = self.healths.get_mut(followed) {
+                        health.revive();
+                    }
+                }
+            }
+            Actions::EntityAction(u64_marker, action) => {
+                if let Some(u64_marker) = u64_marker {
+                    if let Some(ent) = self.u64_marker_allocator.retrieve_entity_internal(u64_marker.id()) {
+                        self.perform_action(ent, action);
+                    }
+                }
+            }
+            Actions::MultipleActions(actions) => {
+                for action in actions.iter() {
+                    self.perform_action(entity, action);
                 }
-                _ => {}
             }
+            Actions::Delay(ticks, action) => {
+                if *ticks == 0 {
+                    self.perform_action(entity, action);
+                } else {
+                    self.scheduled.push((entity, *ticks, (**action).clone()));
+                }
+            }
+            Actions::Repeat(count, interval, action) => {
+                self.perform_action(entity, action);
+
+                if *count != 1 {
+                    let next = Actions::Repeat(count.saturating_sub(1), *interval, action.clone());
+                    self.scheduled.push((entity, (*interval).max(1), next));
+                }
+            }
+            Actions::If(condition, then_action, else_action) => {
+                if self.check_condition(entity, condition) {
+                    self.perform_action(entity, then_action);
+                } else {
+                    self.perform_action(entity, else_action);
+                }
+            }
+            Actions::SetVariable(marker, name, value) => {
+                self.update_variables(entity, marker, |v| v.set(name, value.clone()));
+            }
+            Actions::IncrementVariable(marker, name, amount) => {
+                self.update_variables(entity, marker, |v| v.increment(name, *amount));
+            }
+            Actions::ToggleVariable(marker, name) => {
+                self.update_variables(entity, marker, |v| v.toggle(name));
+            }
+            Actions::SpawnParticles(settings, count) => {
+                if let Some(rect) = self.rects.get(entity) {
+                    let rect = rect.get_rect();
+                    let center = Point2::new(rect.left() + rect.size.x as f64 / 2., rect.top() + rect.size.y as f64 / 2.);
+
+                    self.particle_bursts.request(settings.clone(), center, *count);
+                }
+            }
+            Actions::PlaySound(path, volume) => {
+                let pos = self.rects.get(entity).map(|rect| {
+                    let rect = rect.get_rect();
+                    Point2::new(rect.left() + rect.size.x as f64 / 2., rect.top() + rect.size.y as f64 / 2.)
+                });
+
+                self.sounds.request(path.clone(), *volume, pos);
+            }
+            Actions::Empty => {}
         }
+    }
+}
+
+pub struct ActionSystem;
+
+impl<'a> System<'a> for ActionSystem {
+    type SystemData = (
+        Entities<'a>,
+        Read<'a, U64MarkerAllocator>,
+        Read<'a, FollowEntity>,
+        Read<'a, RespawnPoint>,
+        WriteStorage<'a, RectComponent>,
+        WriteStorage<'a, ActionComponent>,
+        WriteStorage<'a, PhysicsComponent>,
+        WriteStorage<'a, HealthComponent>,
+        ReadStorage<'a, ContactsComponent>,
+        ReadStorage<'a, InventoryComponent>,
+        WriteStorage<'a, VariablesComponent>,
+        Write<'a, ParticleBursts>,
+        Write<'a, SoundQueue>,
+    );
+
+    fn run(&mut self, (entities, u64_marker_allocator, follow_entity, respawn, mut rects, mut actions, mut physics, mut healths, contacts, inventories, mut variables, mut particle_bursts, mut sounds): Self::SystemData) {
+        let mut data = ActionData {
+            entities: &entities,
+            u64_marker_allocator: &u64_marker_allocator,
+            follow_entity: &follow_entity,
+            respawn: &respawn,
+            rects: &mut rects,
+            physics: &mut physics,
+            healths: &mut healths,
+            contacts: &contacts,
+            inventories: &inventories,
+            variables: &mut variables,
+            particle_bursts: &mut particle_bursts,
+            sounds: &mut sounds,
+            scheduled: vec![],
+            held_input: false,
+        };
 
         for (ent, action) in (&*entities, &mut actions).join() {
-            for action in action.actions_remaining.iter() {
-                perform_action(ent, action, &entities, &u64_marker_allocator, &mut rects, &mut physics);
+            for (action, held_input) in action.take_due_actions().iter() {
+                data.held_input = *held_input;
+                data.perform_action(ent, action);
             }
+        }
 
-            action.actions_remaining.clear();
+        for (ent, ticks, action) in data.scheduled.drain(..) {
+            if entities.is_alive(ent) {
+                ActionComponent::schedule_action(&mut actions, ent, ticks, action);
+            }
         }
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,169 @@
+use crate::{
+    ecs::{
+        actions::{
+            ActionComponent,
+            Actions,
+        },
+        chunk::{
+            ActiveChunkMarker,
+            ChunksGrid,
+        },
+        level::{
+            DeltaTime,
+            FollowEntity,
+        },
+        physics::{
+            self,
+            ContactsComponent,
+            PhysicsComponent,
+        },
+        rect::RectComponent,
+        triggers::TriggerComponent,
+    },
+    utils::math::Rect,
+};
+use nalgebra::Vector2;
+use serde::{
+    Deserialize,
+    Serialize,
+};
+use specs::{
+    prelude::*,
+    saveload::{
+        Marker,
+        MarkerAllocator,
+        U64MarkerAllocator,
+    },
+};
+use specs_derive::Component;
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+pub enum AiBehavior {
+    /// Avance jusqu'à un mur ou un vide puis fait demi-tour
+    Patrol,
+    /// Poursuit l'entité suivie lorsqu'elle est à moins de `range` pixels, patrouille sinon
+    Chase { range: f64 },
+}
+
+/// Entité autonome, ses décisions passent par l'`ActionComponent` comme les entrées du joueur.
+#[derive(Component, Serialize, Deserialize, Clone, Debug)]
+pub struct AiComponent {
+    pub behavior: AiBehavior,
+    /// Vitesse de déplacement en pixels par seconde
+    pub speed: f64,
+    /// Saute par-dessus les obstacles moins hauts que `jump_height` au lieu de faire demi-tour
+    pub jump_obstacles: bool,
+    pub jump_height: u32,
+    #[serde(skip)]
+    direction: f64,
+}
+
+impl Default for AiComponent {
+    fn default() -> Self {
+        AiComponent {
+            behavior: AiBehavior::Patrol,
+            speed: 100.,
+            jump_obstacles: false,
+            jump_height: 100,
+            direction: 1.,
+        }
+    }
+}
+
+pub struct AiSystem;
+
+impl AiSystem {
+    /// Rectangle d'un pixel de large collé au côté du corps dans la direction donnée, décalé verticalement de `offset_y`.
+    fn side_probe(rect: Rect, direction: f64, offset_y: f64) -> Rect {
+        let x = if direction > 0. { rect.right() } else { rect.left() - 1. };
+
+        Rect::new(x, rect.top() + 1. + offset_y, 1, rect.size.y.saturating_sub(2))
+    }
+
+    /// Rectangle situé juste sous le bord avant du corps.
+    fn ledge_probe(rect: Rect, direction: f64) -> Rect {
+        let x = if direction > 0. { rect.right() } else { rect.left() - 1. };
+
+        Rect::new(x, rect.bottom(), 1, 2)
+    }
+}
+
+impl<'a> System<'a> for AiSystem {
+    type SystemData = (
+        Entities<'a>,
+        Read<'a, DeltaTime>,
+        Read<'a, FollowEntity>,
+        Read<'a, U64MarkerAllocator>,
+        ReadExpect<'a, ChunksGrid>,
+        WriteStorage<'a, AiComponent>,
+        ReadStorage<'a, RectComponent>,
+        ReadStorage<'a, PhysicsComponent>,
+        ReadStorage<'a, ContactsComponent>,
+        ReadStorage<'a, TriggerComponent>,
+        WriteStorage<'a, ActionComponent>,
+        ReadStorage<'a, ActiveChunkMarker>,
+    );
+
+    fn run(&mut self, (entities, dt, follow_entity, u64_marker_allocator, chunks_grid, mut ais, rects, physics, contacts, triggers, mut actions, active_chunk): Self::SystemData) {
+        let followed_rect = follow_entity.0
+            .and_then(|m| u64_marker_allocator.retrieve_entity_internal(m.id()))
+            .and_then(|e| rects.get(e))
+            .map(|r| r.get_rect());
+
+        for (ent, ai, rect, _) in (&*entities, &mut ais, &rects, &active_chunk).join() {
+            let rect = rect.get_rect();
+            let grounded = contacts.get(ent).map_or(false, |c| c.is_grounded());
+
+            // Mêmes obstacles que ceux qui bloquent le corps dans le `PhysicsSystem`
+            let is_wall = |probe: Rect, direction: f64| physics::is_body_in_rect(probe, ent, &chunks_grid, &rects, &physics, &triggers, |shape| shape.blocks_horizontally(direction));
+            let is_ground = |probe: Rect| physics::is_body_in_rect(probe, ent, &chunks_grid, &rects, &physics, &triggers, |_| true);
+
+            let target_direction = match (ai.behavior, followed_rect) {
+                (AiBehavior::Chase { range }, Some(target)) => {
+                    let dx = (target.left() + target.size.x as f64 / 2.) - (rect.left() + rect.size.x as f64 / 2.);
+                    let dy = (target.top() + target.size.y as f64 / 2.) - (rect.top() + rect.size.y as f64 / 2.);
+
+                    if Vector2::new(dx, dy).norm() <= range && dx.abs() > 1. { Some(dx.signum()) } else { None }
+                }
+                _ => None
+            };
+
+            let mut moving = true;
+
+            match target_direction {
+                Some(direction) => {
+                    ai.direction = direction;
+
+                    // Une poursuite s'arrête au bord du vide plutôt que d'y tomber
+                    if grounded && !is_ground(Self::ledge_probe(rect, ai.direction)) {
+                        moving = false;
+                    }
+                }
+                None => {
+                    if grounded && !is_ground(Self::ledge_probe(rect, ai.direction)) {
+                        ai.direction = -ai.direction;
+                    }
+                }
+            }
+
+            if is_wall(Self::side_probe(rect, ai.direction, 0.), ai.direction) {
+                let can_jump_over = ai.jump_obstacles && !is_wall(Self::side_probe(rect, ai.direction, -(ai.jump_height as f64)), ai.direction);
+
+                if can_jump_over {
+                    // Un saut demandé une seule fois n'est pas coupé et atteint `jump_height`
+                    if grounded {
+                        ActionComponent::push_action(&mut actions, ent, Actions::PhysicsJump(ai.jump_height));
+                    }
+                } else if target_direction.is_some() {
+                    moving = false;
+                } else {
+                    ai.direction = -ai.direction;
+                }
+            }
+
+            if moving {
+                ActionComponent::push_action(&mut actions, ent, Actions::PhysicsMove(Vector2::new(ai.direction * ai.speed * dt.0, 0.)));
+            }
+        }
+    }
+}
@@ -0,0 +1,240 @@
+use crate::ecs::{
+    chunk::ActiveChunkMarker,
+    physics::{
+        BodyType,
+        ContactsComponent,
+        PhysicsComponent,
+    },
+    rect::RectComponent,
+    render::{
+        RenderLayer,
+        SpriteImage,
+    },
+};
+use ggez::graphics;
+use nalgebra::Point2;
+use serde::{
+    Deserialize,
+    Serialize,
+};
+use specs::prelude::*;
+use specs_derive::Component;
+use std::{
+    collections::BTreeMap,
+    num::NonZeroU32,
+};
+
+/// Images de l'animation, numérotées à partir de 0.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub enum FrameSource {
+    /// Grille d'images dans une seule texture, numérotées ligne par ligne
+    SpriteSheet { image: SpriteImage, columns: NonZeroU32, rows: NonZeroU32 },
+    /// Une texture par image
+    Images(Vec<SpriteImage>),
+}
+
+impl FrameSource {
+    pub fn frame_count(&self) -> u32 {
+        match self {
+            FrameSource::SpriteSheet { columns, rows, .. } => columns.get() * rows.get(),
+            FrameSource::Images(images) => images.len() as u32,
+        }
+    }
+
+    /// Texture et sous-rectangle normalisé de l'image.
+    pub fn get_frame(&self, frame: u32) -> Option<(&SpriteImage, graphics::Rect)> {
+        match self {
+            FrameSource::SpriteSheet { image, columns, rows } => {
+                let (columns, rows) = (columns.get(), rows.get());
+
+                if frame >= columns * rows {
+                    return None;
+                }
+
+                let (w, h) = (1. / columns as f32, 1. / rows as f32);
+
+                Some((image, graphics::Rect::new((frame % columns) as f32 * w, (frame / columns) as f32 * h, w, h)))
+            }
+            FrameSource::Images(images) => images.get(frame as usize).map(|image| (image, graphics::Rect::one())),
+        }
+    }
+
+    pub fn images(&self) -> Vec<&SpriteImage> {
+        match self {
+            FrameSource::SpriteSheet { image, .. } => vec![image],
+            FrameSource::Images(images) => images.iter().collect(),
+        }
+    }
+
+    pub fn images_mut(&mut self) -> Vec<&mut SpriteImage> {
+        match self {
+            FrameSource::SpriteSheet { image, .. } => vec![image],
+            FrameSource::Images(images) => images.iter_mut().collect(),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+pub enum LoopMode {
+    Loop,
+    Once,
+    PingPong,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct AnimationClip {
+    pub first_frame: u32,
+    pub frame_count: u32,
+    /// Durée d'une image, en ticks
+    pub frame_ticks: u32,
+    pub loop_mode: LoopMode,
+}
+
+impl Default for AnimationClip {
+    fn default() -> Self {
+        AnimationClip { first_frame: 0, frame_count: 1, frame_ticks: 10, loop_mode: LoopMode::Loop }
+    }
+}
+
+impl AnimationClip {
+    /// Image affichée après `ticks` ticks passés dans ce clip.
+    pub fn frame_at(&self, ticks: u32) -> u32 {
+        let count = self.frame_count.max(1);
+        let n = ticks / self.frame_ticks.max(1);
+
+        let offset = match self.loop_mode {
+            LoopMode::Loop => n % count,
+            LoopMode::Once => n.min(count - 1),
+            LoopMode::PingPong => {
+                if count == 1 {
+                    0
+                } else {
+                    let period = 2 * (count - 1);
+                    let p = n % period;
+                    if p < count { p } else { period - p }
+                }
+            }
+        };
+
+        self.first_frame + offset
+    }
+}
+
+/// États de la machine à états, chacun associé à un clip.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum AnimationState {
+    Idle,
+    Run,
+    Jump,
+    Fall,
+}
+
+impl Default for AnimationState {
+    fn default() -> Self { AnimationState::Idle }
+}
+
+/// Remplace le `SpriteComponent` au rendu, le clip joué est choisi selon le déplacement et les contacts de l'entité.
+#[derive(Component, Serialize, Deserialize, Clone, Debug)]
+pub struct AnimationComponent {
+    pub source: FrameSource,
+    pub clips: BTreeMap<AnimationState, AnimationClip>,
+    #[serde(default)]
+    pub layer: RenderLayer,
+    #[serde(default)]
+    pub z_index: i32,
+    /// Force l'état affiché, utilisé pour l'aperçu dans l'éditeur
+    #[serde(skip)]
+    pub preview: Option<AnimationState>,
+    #[serde(skip)]
+    state: AnimationState,
+    #[serde(skip)]
+    ticks: u32,
+    #[serde(skip)]
+    last_pos: Option<Point2<f64>>,
+}
+
+impl Default for AnimationComponent {
+    fn default() -> Self {
+        let mut clips = BTreeMap::new();
+        clips.insert(AnimationState::Idle, AnimationClip::default());
+
+        AnimationComponent {
+            source: FrameSource::Images(vec![]),
+            clips,
+            layer: RenderLayer::default(),
+            z_index: 0,
+            preview: None,
+            state: AnimationState::Idle,
+            ticks: 0,
+            last_pos: None,
+        }
+    }
+}
+
+impl AnimationComponent {
+    pub fn get_state(&self) -> AnimationState { self.state }
+
+    /// Clip de l'état courant, le clip `Idle` ou le premier clip si l'état n'en a pas.
+    pub fn current_clip(&self) -> Option<&AnimationClip> {
+        self.clips.get(&self.state).or_else(|| self.clips.get(&AnimationState::Idle)).or_else(|| self.clips.values().next())
+    }
+
+    pub fn current_frame(&self) -> u32 {
+        self.current_clip().map_or(0, |clip| clip.frame_at(self.ticks))
+    }
+
+    fn set_state(&mut self, state: AnimationState) {
+        if self.state != state {
+            self.state = state;
+            self.ticks = 0;
+        }
+    }
+}
+
+/// Distance minimale parcourue pendant un tick pour considérer que l'entité se déplace.
+const ANIMATION_MOVE_THRESHOLD: f64 = 0.01;
+
+pub struct AnimationSystem;
+
+impl<'a> System<'a> for AnimationSystem {
+    type SystemData = (
+        Entities<'a>,
+        WriteStorage<'a, AnimationComponent>,
+        ReadStorage<'a, RectComponent>,
+        ReadStorage<'a, PhysicsComponent>,
+        ReadStorage<'a, ContactsComponent>,
+        ReadStorage<'a, ActiveChunkMarker>,
+    );
+
+    fn run(&mut self, (entities, mut animations, rects, physics, contacts, active_chunk): Self::SystemData) {
+        for (ent, animation, rect, _) in (&*entities, &mut animations, &rects, &active_chunk).join() {
+            let pos = rect.pos();
+            let moved = animation.last_pos.map_or(nalgebra::zero(), |last| pos - last);
+            animation.last_pos = Some(pos);
+
+            let state = match animation.preview {
+                Some(state) => state,
+                None => {
+                    let grounded = contacts.get(ent).map_or(false, |c| c.is_grounded());
+                    let velocity_y = physics.get(ent).map_or(0., |p| p.velocity.y);
+                    // Les corps statiques et les plateformes mobiles ne tombent jamais
+                    let dynamic = physics.get(ent).map_or(false, |p| match p.body_type {
+                        BodyType::Dynamic { .. } => true,
+                        BodyType::Static => false
+                    });
+
+                    if !grounded && dynamic {
+                        if velocity_y < 0. || moved.y < -ANIMATION_MOVE_THRESHOLD { AnimationState::Jump } else { AnimationState::Fall }
+                    } else if moved.x.abs() > ANIMATION_MOVE_THRESHOLD {
+                        AnimationState::Run
+                    } else {
+                        AnimationState::Idle
+                    }
+                }
+            };
+
+            animation.set_state(state);
+            animation.ticks = animation.ticks.wrapping_add(1);
+        }
+    }
+}
@@ -0,0 +1,106 @@
+use crate::utils::{
+    camera::Camera,
+    constants,
+};
+use nalgebra::{
+    distance,
+    Point2,
+};
+use serde::{
+    Deserialize,
+    Serialize,
+};
+use std::path::PathBuf;
+
+/// Musique de fond jouée au chargement du niveau.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct MusicConfig {
+    pub path: PathBuf,
+    pub looping: bool,
+    pub volume: f32,
+}
+
+impl Default for MusicConfig {
+    fn default() -> Self {
+        MusicConfig { path: PathBuf::new(), looping: true, volume: 1. }
+    }
+}
+
+/// Sons demandés par les actions pendant le tick, joués par le niveau.
+#[derive(Default)]
+pub struct SoundQueue {
+    /// Chemin, volume et position dans le monde, `None` pour un son non spatialisé
+    requested: Vec<(PathBuf, f32, Option<Point2<f64>>)>,
+}
+
+impl SoundQueue {
+    pub fn request(&mut self, path: PathBuf, volume: f32, pos: Option<Point2<f64>>) {
+        self.requested.push((path, volume, pos));
+    }
+
+    pub fn drain(&mut self) -> Vec<(PathBuf, f32, Option<Point2<f64>>)> {
+        self.requested.drain(..).collect()
+    }
+}
+
+/// Atténuation d'un son émis en `pos` selon sa distance au centre de la vue de la caméra :
+/// plein volume jusqu'à `AUDIO_FULL_VOLUME_DISTANCE`, puis décroissance linéaire jusqu'au silence à `AUDIO_MAX_DISTANCE`.
+pub fn attenuation(camera: &Camera, pos: Point2<f64>) -> f32 {
+    let view = camera.world_view();
+    let center = Point2::new(view.left() + view.size.x as f64 / 2., view.top() + view.size.y as f64 / 2.);
+
+    let (full, max) = (constants::AUDIO_FULL_VOLUME_DISTANCE, constants::AUDIO_MAX_DISTANCE);
+
+    (1. - (distance(&center, &pos) - full) / (max - full)).max(0.).min(1.) as f32
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        ecs::{
+            actions::{
+                ActionComponent,
+                Actions,
+                ActionSystem,
+            },
+            simulation::tests::{
+                create_player,
+                load_test_level,
+            },
+        },
+        utils::math::Rect,
+    };
+    use specs::{
+        Join,
+        RunNow,
+    };
+    use std::path::PathBuf;
+    use super::SoundQueue;
+
+    #[test]
+    fn play_sound_is_drained_by_null_backend() {
+        let mut simulation = load_test_level("play_sound", |world| {
+            create_player(world, Rect::new(0., 0., 50, 50));
+        });
+
+        {
+            let world = simulation.get_level().get_world();
+
+            let player = world.entities().join().next().unwrap();
+            ActionComponent::push_action(&mut world.write_storage::<ActionComponent>(), player, Actions::PlaySound(PathBuf::from("/sons/saut.ogg"), 0.5));
+
+            ActionSystem.run_now(&world.res);
+
+            let queue = world.read_resource::<SoundQueue>();
+            assert_eq!(queue.requested.len(), 1);
+            assert_eq!(queue.requested[0].0, PathBuf::from("/sons/saut.ogg"));
+            // Le son est placé au centre de l'entité
+            assert_eq!(queue.requested[0].2.map(|pos| (pos.x, pos.y)), Some((25., 25.)));
+        }
+
+        // Le niveau sans fenêtre joue les sons sur le backend muet, la file doit être vidée
+        simulation.step();
+
+        assert!(simulation.get_level().get_world().read_resource::<SoundQueue>().requested.is_empty());
+    }
+}
@@ -0,0 +1,160 @@
+use crate::ecs::{
+    chunk::ActiveChunkMarker,
+    level::FollowEntity,
+    physics::PhysicsComponent,
+    rect::RectComponent,
+};
+use nalgebra::{
+    Point2,
+    Vector2,
+};
+use serde::{
+    Deserialize,
+    Serialize,
+};
+use specs::{
+    prelude::*,
+    saveload::{
+        Marker,
+        MarkerAllocator,
+        U64Marker,
+        U64MarkerAllocator,
+    },
+};
+use specs_derive::Component;
+
+/// Point de contrôle activé lorsque l'entité suivie touche son rectangle.
+#[derive(Component, Serialize, Deserialize, Clone, Debug)]
+pub struct CheckpointComponent {
+    /// Position de réapparition relative à la position du point de contrôle
+    pub spawn_offset: Vector2<f64>,
+    /// Sauvegarde la physique de l'entité suivie lors de l'activation, pour la restaurer à la réapparition
+    pub save_physics: bool,
+}
+
+impl Default for CheckpointComponent {
+    fn default() -> Self {
+        CheckpointComponent { spawn_offset: Vector2::new(0., 0.), save_physics: false }
+    }
+}
+
+/// Point de réapparition de l'entité suivie, par défaut sa position au début du niveau.
+#[derive(Default, Clone)]
+pub struct RespawnPoint {
+    pub checkpoint: Option<U64Marker>,
+    /// Entité du point de contrôle actif, reconnu même sans marqueur
+    pub checkpoint_entity: Option<Entity>,
+    pub pos: Option<Point2<f64>>,
+    pub physics: Option<PhysicsComponent>,
+}
+
+pub struct CheckpointSystem;
+
+impl<'a> System<'a> for CheckpointSystem {
+    type SystemData = (
+        Entities<'a>,
+        Read<'a, FollowEntity>,
+        Read<'a, U64MarkerAllocator>,
+        Write<'a, RespawnPoint>,
+        ReadStorage<'a, CheckpointComponent>,
+        ReadStorage<'a, RectComponent>,
+        ReadStorage<'a, PhysicsComponent>,
+        ReadStorage<'a, U64Marker>,
+        ReadStorage<'a, ActiveChunkMarker>,
+    );
+
+    fn run(&mut self, (entities, follow_entity, u64_marker_allocator, mut respawn, checkpoints, rects, physics, markers, active_chunk): Self::SystemData) {
+        let followed = match follow_entity.0.and_then(|m| u64_marker_allocator.retrieve_entity_internal(m.id())) {
+            Some(followed) => followed,
+            None => return
+        };
+
+        let followed_rect = match rects.get(followed) {
+            Some(rect) => rect.get_rect(),
+            None => return
+        };
+
+        if respawn.pos.is_none() {
+            respawn.pos = Some(followed_rect.pos);
+        }
+
+        for (checkpoint_ent, checkpoint, rect, _) in (&*entities, &checkpoints, &rects, &active_chunk).join() {
+            let marker = markers.get(checkpoint_ent).cloned();
+
+            let active = respawn.checkpoint_entity == Some(checkpoint_ent) || (marker.is_some() && respawn.checkpoint.map(|m| m.id()) == marker.map(|m| m.id()));
+
+            if checkpoint_ent == followed || active {
+                continue;
+            }
+
+            if rect.get_rect().overlaps(followed_rect) {
+                respawn.checkpoint = marker;
+                respawn.checkpoint_entity = Some(checkpoint_ent);
+                respawn.pos = Some(rect.pos() + checkpoint.spawn_offset);
+                respawn.physics = if checkpoint.save_physics { physics.get(followed).cloned() } else { None };
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        ecs::{
+            level::FollowEntity,
+            rect::RectComponent,
+            simulation::tests::{
+                create_floor,
+                create_player,
+                load_test_level,
+            },
+        },
+        utils::math::Rect,
+    };
+    use nalgebra::Point2;
+    use specs::{
+        Builder,
+        Join,
+        saveload::{
+            Marker,
+            U64Marker,
+        },
+    };
+    use super::{
+        CheckpointComponent,
+        RespawnPoint,
+    };
+
+    #[test]
+    fn unmarked_checkpoint_activates_once() {
+        let mut simulation = load_test_level("checkpoint_unmarked", |world| {
+            create_player(world, Rect::new(100., 100., 50, 50));
+            create_floor(world, Rect::new(0., 150., 400, 50));
+        });
+
+        {
+            let world = simulation.get_level_mut().get_world_mut();
+
+            // Le joueur est créé en premier, il a le plus petit marqueur
+            let player = world.read_storage::<U64Marker>().join().min_by_key(|m| m.id()).cloned();
+            world.write_resource::<FollowEntity>().0 = player;
+
+            // Les entités sans marqueur ne sont pas enregistrées, le point de contrôle est ajouté après le chargement
+            world.create_entity()
+                .with(RectComponent::new(Rect::new(80., 50., 100, 100)))
+                .with(CheckpointComponent { save_physics: true, ..CheckpointComponent::default() })
+                .build();
+        }
+
+        simulation.run(5);
+
+        let world = simulation.get_level_mut().get_world_mut();
+        assert!(world.read_resource::<RespawnPoint>().checkpoint_entity.is_some());
+        world.write_resource::<RespawnPoint>().pos = Some(Point2::new(-1., -1.));
+
+        // Le point de contrôle déjà actif ne doit pas écraser la réapparition tant que le joueur le touche
+        simulation.run(5);
+
+        assert_eq!(simulation.get_level().get_world().read_resource::<RespawnPoint>().pos, Some(Point2::new(-1., -1.)));
+    }
+}
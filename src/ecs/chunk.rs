@@ -44,6 +44,13 @@ impl ActiveChunksRect {
         }
     }
 
+    pub fn set_rect(&mut self, rect: Rect) {
+        if self.rect.pos != rect.pos || self.rect.size != rect.size {
+            self.rect = rect;
+            self.dirty = true;
+        }
+    }
+
     pub fn update_camera(&mut self, camera: &Camera) {
         let camera_view = camera.world_view();
         self.move_to(camera_view.pos);
@@ -71,32 +78,21 @@ pub struct ChunkComponent {
     pub chunks: Vec<Chunk>
 }
 
-pub struct ChunkSystem {
+/// Grille des chunks, partagée en ressource pour que les autres systèmes puissent interroger les entités d'une zone.
+pub struct ChunksGrid {
     chunks: Array2<Vec<Entity>>,
     chunks_rect: Rect,
     num_cells: (usize, usize),
-    dirty_insert: BitSet,
-    dirty_modify: BitSet,
-    dirty_remove: BitSet,
-    insert_rect_reader: Option<ReaderId<InsertedFlag>>,
-    modify_rect_reader: Option<ReaderId<ModifiedFlag>>,
-    remove_rect_reader: Option<ReaderId<RemovedFlag>>,
 }
 
-impl ChunkSystem {
-    pub fn new((width, height): (usize, usize), _active_rect: Rect) -> Self {
+impl ChunksGrid {
+    pub fn new((width, height): (usize, usize)) -> Self {
         let chunks: Array2<Vec<Entity>> = Array::default((width, height));
         let chunks_rect = Rect::new(0., 0., CHUNK_SIZE * width as u32, CHUNK_SIZE * height as u32);
-        ChunkSystem {
+        ChunksGrid {
             chunks,
             chunks_rect,
             num_cells: (width, height),
-            dirty_insert: BitSet::new(),
-            dirty_modify: BitSet::new(),
-            dirty_remove: BitSet::new(),
-            insert_rect_reader: None,
-            modify_rect_reader: None,
-            remove_rect_reader: None,
         }
     }
 
@@ -115,6 +111,23 @@ impl ChunkSystem {
 
     pub fn get_bounds_chunks(&self) -> Rect { self.chunks_rect }
 
+    /// Entités enregistrées dans les chunks qui chevauchent le rectangle, sans doublon.
+    /// Les positions sont celles du dernier passage du `ChunkSystem`.
+    pub fn get_entities_in_rect(&self, rect: Rect) -> Vec<Entity> {
+        let mut seen = BitSet::new();
+        let mut entities = vec![];
+
+        for chunk in self.get_chunks_overlap_rect(rect).iter() {
+            for ent in self.chunks.get(*chunk).unwrap().iter() {
+                if !seen.add(ent.id()) {
+                    entities.push(*ent);
+                }
+            }
+        }
+
+        entities
+    }
+
     fn get_chunk_rect(chunk: Chunk) -> Rect {
         Rect::new(chunk.0 as f64 * CHUNK_SIZE as f64, chunk.1 as f64 * CHUNK_SIZE as f64, CHUNK_SIZE as u32, CHUNK_SIZE as u32)
     }
@@ -156,25 +169,47 @@ impl ChunkSystem {
 
         overlaps_chunks
     }
+}
+
+pub struct ChunkSystem {
+    dirty_insert: BitSet,
+    dirty_modify: BitSet,
+    dirty_remove: BitSet,
+    insert_rect_reader: Option<ReaderId<InsertedFlag>>,
+    modify_rect_reader: Option<ReaderId<ModifiedFlag>>,
+    remove_rect_reader: Option<ReaderId<RemovedFlag>>,
+}
+
+impl ChunkSystem {
+    pub fn new() -> Self {
+        ChunkSystem {
+            dirty_insert: BitSet::new(),
+            dirty_modify: BitSet::new(),
+            dirty_remove: BitSet::new(),
+            insert_rect_reader: None,
+            modify_rect_reader: None,
+            remove_rect_reader: None,
+        }
+    }
 
-    fn update_active_entities<'a>(&mut self, entities: &Entities<'a>, active_storage: &mut WriteStorage<'a, ActiveChunkMarker>, active_rect: &ActiveChunksRect) {
+    fn update_active_entities<'a>(grid: &mut ChunksGrid, entities: &Entities<'a>, active_storage: &mut WriteStorage<'a, ActiveChunkMarker>, active_rect: &ActiveChunksRect) {
         active_storage.clear();
 
-        for chunk in self.get_chunks_overlap_rect(active_rect.get_rect()).iter() {
-            for ent in self.chunks.get(*chunk).unwrap().clone().iter() {
+        for chunk in grid.get_chunks_overlap_rect(active_rect.get_rect()).iter() {
+            for ent in grid.chunks.get(*chunk).unwrap().clone().iter() {
                 if entities.is_alive(*ent) {
                     active_storage.insert(*ent, ActiveChunkMarker).unwrap();
                 } else {
-                    self.chunks.get_mut(*chunk).unwrap().retain(|e| e != ent);
+                    grid.chunks.get_mut(*chunk).unwrap().retain(|e| e != ent);
                 }
             }
         }
     }
 
-    fn insert_entity_chunks<'a>(&mut self, ent: Entity, chunk_comp: &mut ChunkComponent, rect: Rect, active_storage: &mut WriteStorage<'a, ActiveChunkMarker>, active_rect: &ActiveChunksRect) {
-        let chunks = self.get_chunks_overlap_rect(rect);
+    fn insert_entity_chunks<'a>(grid: &mut ChunksGrid, ent: Entity, chunk_comp: &mut ChunkComponent, rect: Rect, active_storage: &mut WriteStorage<'a, ActiveChunkMarker>, active_rect: &ActiveChunksRect) {
+        let chunks = grid.get_chunks_overlap_rect(rect);
         for chunk in chunks.iter() {
-            self.chunks.get_mut(*chunk).unwrap().push(ent);
+            grid.chunks.get_mut(*chunk).unwrap().push(ent);
         }
 
         chunk_comp.chunks.extend(chunks);
@@ -184,9 +219,9 @@ impl ChunkSystem {
         }
     }
 
-    fn remove_entity_chunks<'a>(&mut self, ent: Entity, chunk_comp: &mut ChunkComponent, active_storage: &mut WriteStorage<'a, ActiveChunkMarker>) {
+    fn remove_entity_chunks<'a>(grid: &mut ChunksGrid, ent: Entity, chunk_comp: &mut ChunkComponent, active_storage: &mut WriteStorage<'a, ActiveChunkMarker>) {
         for chunk in chunk_comp.chunks.drain(..) {
-            self.chunks.get_mut(chunk).unwrap().retain(|e| *e != ent);
+            grid.chunks.get_mut(chunk).unwrap().retain(|e| *e != ent);
         }
 
         if active_storage.contains(ent) {
@@ -194,10 +229,10 @@ impl ChunkSystem {
         }
     }
 
-    fn update_entity_chunks<'a>(&mut self, ent: Entity, chunk_comp: &mut ChunkComponent, rect: Rect, active_storage: &mut WriteStorage<'a, ActiveChunkMarker>, active_rect: &ActiveChunksRect) {
+    fn update_entity_chunks<'a>(grid: &mut ChunksGrid, ent: Entity, chunk_comp: &mut ChunkComponent, rect: Rect, active_storage: &mut WriteStorage<'a, ActiveChunkMarker>, active_rect: &ActiveChunksRect) {
         // TODO pas terrible
-        self.remove_entity_chunks(ent, chunk_comp, active_storage);
-        self.insert_entity_chunks(ent, chunk_comp, rect, active_storage, active_rect);
+        Self::remove_entity_chunks(grid, ent, chunk_comp, active_storage);
+        Self::insert_entity_chunks(grid, ent, chunk_comp, rect, active_storage, active_rect);
     }
 }
 
@@ -208,9 +243,10 @@ impl<'a> System<'a> for ChunkSystem {
         WriteStorage<'a, ChunkComponent>,
         WriteStorage<'a, ActiveChunkMarker>,
         WriteExpect<'a, ActiveChunksRect>,
+        WriteExpect<'a, ChunksGrid>,
     );
 
-    fn run(&mut self, (entities, rect, mut chunk, mut active_chunk, mut active_rect): Self::SystemData) {
+    fn run(&mut self, (entities, rect, mut chunk, mut active_chunk, mut active_rect, mut grid): Self::SystemData) {
         self.dirty_insert.clear();
         self.dirty_modify.clear();
         self.dirty_remove.clear();
@@ -221,20 +257,20 @@ impl<'a> System<'a> for ChunkSystem {
 
         for (ent, rect, _) in (&*entities, &rect, self.dirty_insert.clone()).join() {
             let mut comp = ChunkComponent::default();
-            self.insert_entity_chunks(ent, &mut comp, rect.get_rect(), &mut active_chunk, &active_rect);
+            Self::insert_entity_chunks(&mut grid, ent, &mut comp, rect.get_rect(), &mut active_chunk, &active_rect);
             chunk.insert(ent, comp).unwrap();
         }
 
         for (ent, chunk, rect, _) in (&*entities, &mut chunk, &rect, self.dirty_modify.clone()).join() {
-            self.update_entity_chunks(ent, chunk, rect.get_rect(), &mut active_chunk, &active_rect);
+            Self::update_entity_chunks(&mut grid, ent, chunk, rect.get_rect(), &mut active_chunk, &active_rect);
         }
 
         for (ent, chunk, _) in (&*entities, &mut chunk, self.dirty_remove.clone()).join() {
-            self.remove_entity_chunks(ent, chunk, &mut active_chunk);
+            Self::remove_entity_chunks(&mut grid, ent, chunk, &mut active_chunk);
         }
 
         if active_rect.update_dirty() {
-            self.update_active_entities(&entities, &mut active_chunk, &active_rect);
+            Self::update_active_entities(&mut grid, &entities, &mut active_chunk, &active_rect);
         }
     }
 
@@ -0,0 +1,246 @@
+use crate::ecs::{
+    actions::{
+        ActionComponent,
+        Actions,
+    },
+    chunk::{
+        ActiveChunkMarker,
+        ChunksGrid,
+    },
+    goals::LevelProgress,
+    physics::{
+        ContactsComponent,
+        PhysicsComponent,
+    },
+    rect::RectComponent,
+};
+use serde::{
+    Deserialize,
+    Serialize,
+};
+use specs::prelude::*;
+use specs_derive::Component;
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+pub enum CollectibleKind {
+    Coin,
+    /// Clé ouvrant les portes de même identifiant
+    Key(u32),
+    Item,
+}
+
+/// Objet ramassé par les entités possédant un inventaire lorsqu'elles le touchent.
+#[derive(Component, Serialize, Deserialize, Clone, Debug)]
+pub struct CollectibleComponent {
+    pub kind: CollectibleKind,
+    /// Points ajoutés au score
+    pub value: u32,
+    /// Action effectuée sur l'entité qui ramasse l'objet
+    pub on_pickup: Actions,
+}
+
+impl Default for CollectibleComponent {
+    fn default() -> Self {
+        CollectibleComponent { kind: CollectibleKind::Coin, value: 10, on_pickup: Actions::Empty }
+    }
+}
+
+#[derive(Component, Serialize, Deserialize, Clone, Debug, Default)]
+pub struct InventoryComponent {
+    pub coins: u32,
+    pub items: u32,
+    pub keys: Vec<u32>,
+}
+
+impl InventoryComponent {
+    pub fn add(&mut self, kind: CollectibleKind) {
+        match kind {
+            CollectibleKind::Coin => self.coins += 1,
+            CollectibleKind::Key(id) => self.keys.push(id),
+            CollectibleKind::Item => self.items += 1,
+        }
+    }
+
+    pub fn has_key(&self, id: u32) -> bool { self.keys.contains(&id) }
+
+    /// Retire une clé de l'inventaire, retourne faux si l'inventaire ne la contient pas.
+    pub fn use_key(&mut self, id: u32) -> bool {
+        match self.keys.iter().position(|k| *k == id) {
+            Some(index) => {
+                self.keys.remove(index);
+                true
+            }
+            None => false
+        }
+    }
+}
+
+/// Porte bloquante ouverte par une entité dont l'inventaire contient la clé, le corps physique de la porte est alors retiré.
+#[derive(Component, Serialize, Deserialize, Clone, Debug)]
+pub struct DoorComponent {
+    pub key: u32,
+    /// La clé est retirée de l'inventaire à l'ouverture
+    pub consume_key: bool,
+}
+
+impl Default for DoorComponent {
+    fn default() -> Self {
+        DoorComponent { key: 0, consume_key: true }
+    }
+}
+
+/// Score de la partie en cours.
+#[derive(Default, Clone, Copy, Debug)]
+pub struct Score(pub u32);
+
+pub struct CollectibleSystem;
+
+impl<'a> System<'a> for CollectibleSystem {
+    type SystemData = (
+        Entities<'a>,
+        ReadExpect<'a, ChunksGrid>,
+        Write<'a, Score>,
+        Write<'a, LevelProgress>,
+        WriteStorage<'a, InventoryComponent>,
+        ReadStorage<'a, CollectibleComponent>,
+        ReadStorage<'a, DoorComponent>,
+        WriteStorage<'a, PhysicsComponent>,
+        ReadStorage<'a, ContactsComponent>,
+        ReadStorage<'a, RectComponent>,
+        WriteStorage<'a, ActionComponent>,
+        ReadStorage<'a, ActiveChunkMarker>,
+    );
+
+    fn run(&mut self, (entities, chunks_grid, mut score, mut progress, mut inventories, collectibles, doors, mut physics, contacts, rects, mut actions, active_chunk): Self::SystemData) {
+        let mut picked: Vec<Entity> = vec![];
+
+        for (ent, inventory, rect, _) in (&*entities, &mut inventories, &rects, &active_chunk).join() {
+            let rect = rect.get_rect();
+
+            for collectible_ent in chunks_grid.get_entities_in_rect(rect) {
+                if collectible_ent == ent || picked.contains(&collectible_ent) || !entities.is_alive(collectible_ent) {
+                    continue;
+                }
+
+                if let (Some(collectible), Some(collectible_rect)) = (collectibles.get(collectible_ent), rects.get(collectible_ent)) {
+                    if !collectible_rect.get_rect().overlaps(rect) {
+                        continue;
+                    }
+
+                    inventory.add(collectible.kind);
+                    score.0 += collectible.value;
+                    progress.collected_items += 1;
+
+                    ActionComponent::push_action(&mut actions, ent, collectible.on_pickup.clone());
+
+                    entities.delete(collectible_ent).unwrap();
+                    picked.push(collectible_ent);
+                }
+            }
+
+            if let Some(contacts) = contacts.get(ent) {
+                for contact in contacts.contacts.iter() {
+                    if !physics.contains(contact.entity) {
+                        continue;
+                    }
+
+                    if let Some(door) = doors.get(contact.entity) {
+                        let opened = if door.consume_key { inventory.use_key(door.key) } else { inventory.has_key(door.key) };
+
+                        if opened {
+                            physics.remove(contact.entity);
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        ecs::{
+            actions::Actions,
+            inputs::InputComponent,
+            physics::{
+                BodyType,
+                PhysicsComponent,
+            },
+            rect::RectComponent,
+            simulation::{
+                Simulation,
+                tests::{
+                    create_floor,
+                    load_test_level,
+                },
+            },
+        },
+        utils::{
+            constants,
+            math::Rect,
+        },
+    };
+    use ggez::event::Keycode;
+    use specs::{
+        Builder,
+        Join,
+        saveload::{
+            MarkedBuilder,
+            U64Marker,
+        },
+    };
+    use super::{
+        DoorComponent,
+        InventoryComponent,
+    };
+
+    /// Joueur tenant la flèche droite face à une porte fermée par la clé 1.
+    fn walk_to_door(name: &str, keys: Vec<u32>) -> Simulation<'static, 'static> {
+        let mut simulation = load_test_level(name, |world| {
+            world.create_entity()
+                .with(RectComponent::new(Rect::new(100., 250., 50, 50)))
+                .with(PhysicsComponent::new(BodyType::Dynamic { apply_gravity: true }))
+                .with(InputComponent::new(vec![
+                    (Keycode::Right, true, Actions::PhysicsForce([1., 0.].into())),
+                    (Keycode::Right, false, Actions::PhysicsForce([1., 0.].into())),
+                ]))
+                .with(InventoryComponent { coins: 0, items: 0, keys })
+                .marked::<U64Marker>()
+                .build();
+
+            world.create_entity()
+                .with(RectComponent::new(Rect::new(300., 150., 50, 150)))
+                .with(PhysicsComponent::new(BodyType::Static))
+                .with(DoorComponent { key: 1, consume_key: true })
+                .marked::<U64Marker>()
+                .build();
+
+            create_floor(world, Rect::new(0., 300., 2000, 50));
+        });
+
+        simulation.get_input_manager().lock().unwrap().update_key(Keycode::Right, true);
+        simulation.run(constants::DESIRED_FPS as u64 * 2);
+
+        simulation
+    }
+
+    #[test]
+    fn player_with_key_walks_through_door() {
+        let simulation = walk_to_door("door_with_key", vec![1]);
+
+        let (_, player) = simulation.entities_rects()[0];
+        assert!(player.left() > 350., "le joueur doit avoir franchi la porte, gauche = {}", player.left());
+
+        let world = simulation.get_level().get_world();
+        assert!(world.read_storage::<InventoryComponent>().join().all(|inventory| inventory.keys.is_empty()), "la clé doit être consommée");
+    }
+
+    #[test]
+    fn player_without_key_is_blocked_by_door() {
+        let simulation = walk_to_door("door_without_key", vec![]);
+
+        let (_, player) = simulation.entities_rects()[0];
+        assert!(player.right() <= 300. + constants::PHYSICS_EPSILON, "la porte doit bloquer le joueur, droite = {}", player.right());
+    }
+}
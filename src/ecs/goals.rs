@@ -0,0 +1,128 @@
+use crate::ecs::{
+    chunk::ChunksGrid,
+    level::{
+        DeltaTime,
+        FollowEntity,
+    },
+    rect::RectComponent,
+};
+use serde::{
+    Deserialize,
+    Serialize,
+};
+use specs::{
+    prelude::*,
+    saveload::{
+        Marker,
+        MarkerAllocator,
+        U64Marker,
+        U64MarkerAllocator,
+    },
+};
+
+/// Conditions à remplir pour gagner, toutes doivent être remplies.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub enum WinCondition {
+    /// L'entité suivie touche l'entité de sortie
+    ReachExit(Option<U64Marker>),
+    CollectItems(u32),
+    /// Survivre pendant le nombre de secondes donné
+    Survive(f64),
+}
+
+/// Conditions qui font perdre, une seule suffit.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub enum LoseCondition {
+    /// L'entité suivie tombe sous les limites du niveau
+    FallOutOfBounds,
+    /// Le niveau n'est pas terminé après le nombre de secondes donné
+    TimeLimit(f64),
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct LevelGoals {
+    pub win: Vec<WinCondition>,
+    pub lose: Vec<LoseCondition>,
+}
+
+impl Default for LevelGoals {
+    fn default() -> Self {
+        LevelGoals { win: vec![], lose: vec![LoseCondition::FallOutOfBounds] }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum LevelStatus {
+    Playing,
+    Won,
+    Lost,
+}
+
+/// Avancement de la partie en cours, mis à jour par le `GoalsSystem`.
+#[derive(Debug, Clone)]
+pub struct LevelProgress {
+    pub status: LevelStatus,
+    pub collected_items: u32,
+    pub elapsed_time: f64,
+    /// Mis à vrai par les systèmes de jeu lorsque l'entité suivie meurt
+    pub dead: bool,
+}
+
+impl Default for LevelProgress {
+    fn default() -> Self {
+        LevelProgress { status: LevelStatus::Playing, collected_items: 0, elapsed_time: 0., dead: false }
+    }
+}
+
+pub struct GoalsSystem;
+
+impl<'a> System<'a> for GoalsSystem {
+    type SystemData = (
+        Read<'a, DeltaTime>,
+        Read<'a, LevelGoals>,
+        Read<'a, FollowEntity>,
+        Read<'a, U64MarkerAllocator>,
+        ReadExpect<'a, ChunksGrid>,
+        Write<'a, LevelProgress>,
+        ReadStorage<'a, RectComponent>,
+    );
+
+    fn run(&mut self, (dt, goals, follow_entity, u64_marker_allocator, chunks_grid, mut progress, rects): Self::SystemData) {
+        if progress.status != LevelStatus::Playing {
+            return;
+        }
+
+        progress.elapsed_time += dt.0;
+
+        let get_rect = |marker: Option<U64Marker>| {
+            marker.and_then(|m| u64_marker_allocator.retrieve_entity_internal(m.id())).and_then(|e| rects.get(e)).map(|r| r.get_rect())
+        };
+
+        let followed_rect = get_rect(follow_entity.0);
+
+        // L'entité suivie a été supprimée
+        if follow_entity.0.is_some() && followed_rect.is_none() {
+            progress.dead = true;
+        }
+
+        let lost = progress.dead || goals.lose.iter().any(|condition| match condition {
+            LoseCondition::FallOutOfBounds => followed_rect.map_or(false, |r| r.top() > chunks_grid.get_bounds_chunks().bottom()),
+            LoseCondition::TimeLimit(seconds) => progress.elapsed_time >= *seconds,
+        });
+
+        let won = !goals.win.is_empty() && goals.win.iter().all(|condition| match condition {
+            WinCondition::ReachExit(exit) => match (followed_rect, get_rect(*exit)) {
+                (Some(followed_rect), Some(exit_rect)) => followed_rect.overlaps(exit_rect),
+                _ => false
+            },
+            WinCondition::CollectItems(count) => progress.collected_items >= *count,
+            WinCondition::Survive(seconds) => progress.elapsed_time >= *seconds,
+        });
+
+        if lost {
+            progress.status = LevelStatus::Lost;
+        } else if won {
+            progress.status = LevelStatus::Won;
+        }
+    }
+}
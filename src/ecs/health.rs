@@ -0,0 +1,144 @@
+use crate::ecs::{
+    actions::{
+        ActionComponent,
+        Actions,
+    },
+    chunk::ActiveChunkMarker,
+    physics::ContactsComponent,
+};
+use serde::{
+    Deserialize,
+    Serialize,
+};
+use specs::prelude::*;
+use specs_derive::Component;
+
+/// Action effectuée lorsque les points de vie tombent à zéro.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+pub enum DeathBehavior {
+    DeleteEntity,
+    /// Réapparition au dernier point de contrôle, seulement pour l'entité suivie
+    Respawn,
+}
+
+#[derive(Component, Serialize, Deserialize, Clone, Debug)]
+pub struct HealthComponent {
+    pub max: u32,
+    pub current: u32,
+    /// Nombre de ticks d'invulnérabilité après avoir reçu des dégâts
+    pub invulnerability_ticks: u32,
+    pub on_death: DeathBehavior,
+    /// Action effectuée par l'entité juste avant sa mort (particules..)
+    #[serde(default)]
+    pub death_action: Actions,
+    #[serde(skip)]
+    invulnerable_remaining: u32,
+}
+
+impl Default for HealthComponent {
+    fn default() -> Self {
+        HealthComponent { max: 3, current: 3, invulnerability_ticks: 60, on_death: DeathBehavior::DeleteEntity, death_action: Actions::Empty, invulnerable_remaining: 0 }
+    }
+}
+
+impl HealthComponent {
+    pub fn is_invulnerable(&self) -> bool { self.invulnerable_remaining > 0 }
+
+    pub fn is_dead(&self) -> bool { self.current == 0 }
+
+    /// Retire les points de vie si l'entité n'est pas invulnérable, retourne vrai si l'entité vient de mourir.
+    pub fn damage(&mut self, amount: u32) -> bool {
+        if self.is_invulnerable() || self.is_dead() {
+            return false;
+        }
+
+        self.current = self.current.saturating_sub(amount);
+        self.invulnerable_remaining = self.invulnerability_ticks;
+
+        self.is_dead()
+    }
+
+    pub fn heal(&mut self, amount: u32) {
+        self.current = self.current.saturating_add(amount).min(self.max);
+    }
+
+    /// Retourne vrai si l'entité n'était pas déjà morte.
+    pub fn kill(&mut self) -> bool {
+        let was_alive = !self.is_dead();
+        self.current = 0;
+        was_alive
+    }
+
+    pub fn revive(&mut self) {
+        self.current = self.max;
+        self.invulnerable_remaining = self.invulnerability_ticks;
+    }
+}
+
+/// Dégâts infligés aux entités avec de la vie qui touchent cette entité (pics, ennemis..).
+#[derive(Component, Serialize, Deserialize, Clone, Debug)]
+pub struct DamageComponent {
+    pub amount: u32,
+}
+
+impl Default for DamageComponent {
+    fn default() -> Self {
+        DamageComponent { amount: 1 }
+    }
+}
+
+/// Transforme les contacts du `PhysicsSystem` avec des entités dangereuses en `Actions::Damage`.
+pub struct HealthSystem;
+
+impl<'a> System<'a> for HealthSystem {
+    type SystemData = (
+        Entities<'a>,
+        WriteStorage<'a, HealthComponent>,
+        ReadStorage<'a, DamageComponent>,
+        ReadStorage<'a, ContactsComponent>,
+        WriteStorage<'a, ActionComponent>,
+        ReadStorage<'a, ActiveChunkMarker>,
+    );
+
+    fn run(&mut self, (entities, mut healths, damages, contacts, mut actions, active_chunk): Self::SystemData) {
+        for (ent, health, _) in (&*entities, &mut healths, &active_chunk).join() {
+            if health.invulnerable_remaining > 0 {
+                health.invulnerable_remaining -= 1;
+                continue;
+            }
+
+            if let Some(contacts) = contacts.get(ent) {
+                let amount: u32 = contacts.contacts.iter().filter_map(|c| damages.get(c.entity)).map(|d| d.amount).max().unwrap_or(0);
+
+                if amount > 0 {
+                    ActionComponent::push_action(&mut actions, ent, Actions::Damage(amount));
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::HealthComponent;
+
+    #[test]
+    fn heal_is_capped_at_max() {
+        let mut health = HealthComponent { current: 1, ..HealthComponent::default() };
+
+        health.heal(1);
+        assert_eq!(health.current, 2);
+
+        health.heal(5);
+        assert_eq!(health.current, health.max);
+    }
+
+    #[test]
+    fn heal_does_not_overflow() {
+        let mut health = HealthComponent { current: 2, ..HealthComponent::default() };
+
+        health.heal(u32::max_value());
+
+        assert_eq!(health.current, health.max);
+    }
+}
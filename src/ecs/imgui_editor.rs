@@ -1,18 +1,75 @@
 use crate::ecs::{
-    actions::Actions,
+    actions::{
+        Actions,
+        Condition,
+    },
+    ai::{
+        AiBehavior,
+        AiComponent,
+    },
+    animation::{
+        AnimationClip,
+        AnimationComponent,
+        AnimationState,
+        FrameSource,
+        LoopMode,
+    },
+    checkpoints::CheckpointComponent,
+    collectibles::{
+        CollectibleComponent,
+        CollectibleKind,
+        DoorComponent,
+        InventoryComponent,
+    },
+    health::{
+        DamageComponent,
+        DeathBehavior,
+        HealthComponent,
+    },
     inputs::InputComponent,
+    lighting::{
+        LightComponent,
+        LightShape,
+    },
+    particles::{
+        ParticleEmitterComponent,
+        ParticleSettings,
+    },
+    path::{
+        PathComponent,
+        PathMode,
+    },
     physics::{
         BodyType,
+        CollisionShape,
         PhysicsComponent,
+        SlopeAngle,
+        SlopeDirection,
     },
     rect::RectComponent,
     render::{
+        RenderLayer,
         SpriteComponent,
+        SpriteImage,
         SpriteMode,
     },
+    scripting::ScriptComponent,
+    triggers::{
+        TriggerComponent,
+        TriggerFilter,
+        TriggerTarget,
+    },
+    variables::{
+        VariablesComponent,
+        VariableValue,
+    },
+};
+use ggez::{
+    event::Keycode,
+    graphics::Color,
 };
-use ggez::event::Keycode;
 use imgui::{
+    EditableColor,
     ImStr,
     ImString,
     Ui,
@@ -20,9 +77,14 @@ use imgui::{
 use imgui::im_str;
 use lazy_static::lazy_static;
 use nalgebra::Vector2;
+use specs::saveload::{
+    Marker,
+    U64Marker,
+};
 use std::{
     collections::HashMap,
     num::NonZeroU32,
+    path::PathBuf,
 };
 
 trait EnumCombo where Self: Sized {
@@ -95,11 +157,77 @@ impl_enum_ui_combo_wrapper!(ActionsWrapperMod, ActionsWrapperImStr, Actions, "ac
     Move => "Déplacement"; Actions::Move(_), Actions::Move(Vector2::new(0., 0.)),
     PhysicsMove => "Déplacement physique"; Actions::PhysicsMove(_), Actions::PhysicsMove(Vector2::new(0., 0.)),
     PhysicsJump => "Saut physique"; Actions::PhysicsJump(_), Actions::PhysicsJump(0),
+    PhysicsImpulse => "Impulsion physique"; Actions::PhysicsImpulse(_), Actions::PhysicsImpulse(Vector2::new(0., 0.)),
+    PhysicsForce => "Force physique"; Actions::PhysicsForce(_), Actions::PhysicsForce(Vector2::new(0., 0.)),
+    PhysicsDropThrough => "Traverser la plateforme"; Actions::PhysicsDropThrough, Actions::PhysicsDropThrough,
     DeleteEntity => "Supprimer l'entité"; Actions::DeleteEntity, Actions::DeleteEntity,
+    Damage => "Dégâts"; Actions::Damage(_), Actions::Damage(1),
+    Heal => "Soin"; Actions::Heal(_), Actions::Heal(1),
+    Kill => "Tuer"; Actions::Kill, Actions::Kill,
+    Respawn => "Réapparition"; Actions::Respawn, Actions::Respawn,
     MultipleActions => "Actions multiple"; Actions::MultipleActions(_), Actions::MultipleActions(vec![]),
-    EntityAction => "Actions sur une entité"; Actions::EntityAction(_, _), Actions::EntityAction(None, Box::new(Actions::Empty))
+    EntityAction => "Actions sur une entité"; Actions::EntityAction(_, _), Actions::EntityAction(None, Box::new(Actions::Empty)),
+    Delay => "Action différée"; Actions::Delay(_, _), Actions::Delay(60, Box::new(Actions::Empty)),
+    Repeat => "Action répétée"; Actions::Repeat(_, _, _), Actions::Repeat(0, 60, Box::new(Actions::Empty)),
+    If => "Condition"; Actions::If(_, _, _), Actions::If(Condition::OnGround, Box::new(Actions::Empty), Box::new(Actions::Empty)),
+    SetVariable => "Modifier une variable"; Actions::SetVariable(_, _, _), Actions::SetVariable(None, String::new(), VariableValue::Bool(true)),
+    IncrementVariable => "Incrémenter une variable"; Actions::IncrementVariable(_, _, _), Actions::IncrementVariable(None, String::new(), 1),
+    ToggleVariable => "Inverser une variable"; Actions::ToggleVariable(_, _), Actions::ToggleVariable(None, String::new()),
+    SpawnParticles => "Particules"; Actions::SpawnParticles(_, _), Actions::SpawnParticles(ParticleSettings::default(), 20),
+    PlaySound => "Son"; Actions::PlaySound(_, _), Actions::PlaySound(PathBuf::new(), 1.)
 ]);
 
+impl_enum_ui_combo_wrapper!(ConditionWrapper, ConditionWrapperImStr, Condition, "condition"; [
+    CondOnGround => "Au sol"; Condition::OnGround, Condition::OnGround,
+    CondHealthBelow => "Vie inférieure à"; Condition::HealthBelow(_), Condition::HealthBelow(1),
+    CondHasKey => "Possède la clé"; Condition::HasKey(_), Condition::HasKey(0),
+    CondHasCoins => "Possède des pièces"; Condition::HasCoins(_), Condition::HasCoins(1),
+    CondEntityAlive => "Entité en vie"; Condition::EntityAlive(_), Condition::EntityAlive(None),
+    CondVariableEquals => "Variable égale à"; Condition::VariableEquals(_, _, _), Condition::VariableEquals(None, String::new(), VariableValue::Bool(true)),
+    CondVariableAtLeast => "Variable supérieure ou égale à"; Condition::VariableAtLeast(_, _, _), Condition::VariableAtLeast(None, String::new(), 0.),
+    CondNot => "Non"; Condition::Not(_), Condition::Not(Box::new(Condition::OnGround))
+]);
+
+fn draw_ui_u32(label: &ImStr, value: &mut u32, max: i32, ui: &Ui) {
+    let mut value_i32 = *value as i32;
+    if ui.slider_int(label, &mut value_i32, 0, max).build() {
+        *value = value_i32 as u32;
+    }
+}
+
+fn draw_ui_condition(condition: &mut Condition, ui: &Ui) {
+    if let Some(c) = condition.draw_ui_combo(ui) {
+        *condition = c;
+    }
+
+    match *condition {
+        Condition::OnGround => {}
+        Condition::HealthBelow(ref mut hp) => draw_ui_u32(im_str!("vie"), hp, 100, ui),
+        Condition::HasKey(ref mut key) => draw_ui_key(key, ui),
+        Condition::HasCoins(ref mut coins) => draw_ui_u32(im_str!("pièces"), coins, 1000, ui),
+        Condition::EntityAlive(ref mut marker) => draw_ui_marker(marker, ui),
+        Condition::VariableEquals(ref mut marker, ref mut name, ref mut value) => {
+            draw_ui_marker(marker, ui);
+            draw_ui_string(im_str!("variable"), name, ui);
+            draw_ui_variable_value(value, ui);
+        }
+        Condition::VariableAtLeast(ref mut marker, ref mut name, ref mut min) => {
+            draw_ui_marker(marker, ui);
+            draw_ui_string(im_str!("variable"), name, ui);
+
+            let mut min_f32 = *min as f32;
+            if ui.input_float(im_str!("minimum"), &mut min_f32).build() {
+                *min = min_f32 as f64;
+            }
+        }
+        Condition::Not(ref mut condition) => {
+            ui.tree_node(im_str!("non")).build(|| {
+                draw_ui_condition(condition, ui);
+            });
+        }
+    }
+}
+
 fn draw_ui_action(mut action: Actions, popup_id: &ImStr, ui: &Ui) -> Actions {
     if ui.button(im_str!("action"), (100., 0.)) {
         ui.open_popup(popup_id);
@@ -142,9 +270,91 @@ fn draw_ui_action(mut action: Actions, popup_id: &ImStr, ui: &Ui) -> Actions {
                     *height = height_i32 as u32;
                 }
             }
+            Actions::PhysicsImpulse(ref mut impulse) => {
+                let mut x = impulse.x as f32;
+                let mut y = impulse.y as f32;
+
+                if ui.slider_float(im_str!("impulse x"), &mut x, -2000., 2000.).build() {
+                    impulse.x = x as f64;
+                }
+                if ui.slider_float(im_str!("impulse y"), &mut y, -2000., 2000.).build() {
+                    impulse.y = y as f64;
+                }
+            }
+            Actions::PhysicsForce(ref mut force) => {
+                let mut x = force.x as f32;
+                let mut y = force.y as f32;
+
+                if ui.slider_float(im_str!("force x"), &mut x, -1., 1.).build() {
+                    force.x = x as f64;
+                }
+                if ui.slider_float(im_str!("force y"), &mut y, -1., 1.).build() {
+                    force.y = y as f64;
+                }
+            }
+            Actions::PhysicsDropThrough => {}
             Actions::DeleteEntity => {}
+            Actions::Damage(ref mut amount) | Actions::Heal(ref mut amount) => {
+                let mut amount_i32 = *amount as i32;
+                if ui.slider_int(im_str!("amount"), &mut amount_i32, 0, 100).build() {
+                    *amount = amount_i32 as u32;
+                }
+            }
+            Actions::Kill => {}
+            Actions::Respawn => {}
             Actions::EntityAction(_, _) => {}
             Actions::MultipleActions(_) => {}
+            Actions::Delay(ref mut ticks, ref mut delayed) => {
+                draw_ui_u32(im_str!("ticks"), ticks, 1000, ui);
+
+                **delayed = draw_ui_action((**delayed).clone(), im_str!("delayed action"), ui);
+            }
+            Actions::Repeat(ref mut count, ref mut interval, ref mut repeated) => {
+                draw_ui_u32(im_str!("count (0 = infini)"), count, 100, ui);
+                draw_ui_u32(im_str!("interval"), interval, 1000, ui);
+
+                **repeated = draw_ui_action((**repeated).clone(), im_str!("repeated action"), ui);
+            }
+            Actions::If(ref mut condition, ref mut then_action, ref mut else_action) => {
+                draw_ui_condition(condition, ui);
+
+                ui.text("Alors :");
+                ui.same_line(0.);
+                **then_action = draw_ui_action((**then_action).clone(), im_str!("then action"), ui);
+                ui.text("Sinon :");
+                ui.same_line(0.);
+                **else_action = draw_ui_action((**else_action).clone(), im_str!("else action"), ui);
+            }
+            Actions::SetVariable(ref mut marker, ref mut name, ref mut value) => {
+                draw_ui_marker(marker, ui);
+                draw_ui_string(im_str!("variable"), name, ui);
+                draw_ui_variable_value(value, ui);
+            }
+            Actions::IncrementVariable(ref mut marker, ref mut name, ref mut amount) => {
+                draw_ui_marker(marker, ui);
+                draw_ui_string(im_str!("variable"), name, ui);
+
+                let mut amount_i32 = *amount as i32;
+                if ui.input_int(im_str!("amount"), &mut amount_i32).build() {
+                    *amount = amount_i32 as i64;
+                }
+            }
+            Actions::ToggleVariable(ref mut marker, ref mut name) => {
+                draw_ui_marker(marker, ui);
+                draw_ui_string(im_str!("variable"), name, ui);
+            }
+            Actions::SpawnParticles(ref mut settings, ref mut count) => {
+                draw_ui_u32(im_str!("nombre"), count, 500, ui);
+                draw_ui_particle_settings(settings, ui);
+            }
+            Actions::PlaySound(ref mut path, ref mut volume) => {
+                let mut path_str = path.to_string_lossy().into_owned();
+                if draw_ui_string(im_str!("son"), &mut path_str, ui) {
+                    *path = path_str.into();
+                }
+
+                ui.slider_float(im_str!("volume"), volume, 0., 1.).build();
+            }
         }
     });
 
@@ -217,8 +427,24 @@ impl_enum_ui_combo_wrapper!(SpriteModeWrapper, SpriteModeWrapperImStr, SpriteMod
     Repeat => "Répéter"; SpriteMode::Repeat { .. }, SpriteMode::Repeat { x: NonZeroU32::new(1).unwrap(), y: NonZeroU32::new(1).unwrap() }
 ]);
 
+impl_enum_ui_combo_wrapper!(RenderLayerWrapper, RenderLayerWrapperImStr, RenderLayer, "calque"; [
+    LayerBackground => "Arrière-plan"; RenderLayer::Background, RenderLayer::Background,
+    LayerGameplay => "Jeu"; RenderLayer::Gameplay, RenderLayer::Gameplay,
+    LayerForeground => "Premier plan"; RenderLayer::Foreground, RenderLayer::Foreground
+]);
+
+fn draw_ui_render_layer(layer: &mut RenderLayer, z_index: &mut i32, ui: &Ui) {
+    if let Some(l) = layer.draw_ui_combo(ui) {
+        *layer = l;
+    }
+
+    ui.input_int(im_str!("profondeur"), z_index).build();
+}
+
 impl ImGuiEditor for SpriteComponent {
     fn draw_ui(&mut self, ui: &Ui) {
+        draw_ui_render_layer(&mut self.layer, &mut self.z_index, ui);
+
         if let Some(m) = self.mode.draw_ui_combo(ui) {
             self.mode = m;
         }
@@ -247,7 +473,23 @@ impl ImGuiEditor for SpriteComponent {
 
 impl_enum_ui_combo_wrapper!(BodyTypeWrapper, BodyTypeWrapperImStr, BodyType, "type"; [
     Static => "Statique"; BodyType::Static, BodyType::Static,
-    Dynamic => "Dynamique"; BodyType::Dynamic { .. }, BodyType::Dynamic { apply_gravity: false, jump_data: None }
+    Dynamic => "Dynamique"; BodyType::Dynamic { .. }, BodyType::Dynamic { apply_gravity: false }
+]);
+
+impl_enum_ui_combo_wrapper!(CollisionShapeWrapper, CollisionShapeWrapperImStr, CollisionShape, "forme"; [
+    BoxShape => "Boîte"; CollisionShape::Box, CollisionShape::Box,
+    OneWayShape => "Plateforme traversable"; CollisionShape::OneWay, CollisionShape::OneWay,
+    SlopeShape => "Pente"; CollisionShape::Slope(_, _), CollisionShape::Slope(SlopeDirection::UpRight, SlopeAngle::Steep)
+]);
+
+impl_enum_ui_combo_wrapper!(SlopeDirectionWrapper, SlopeDirectionWrapperImStr, SlopeDirection, "direction"; [
+    SlopeUpRight => "Monte vers la droite"; SlopeDirection::UpRight, SlopeDirection::UpRight,
+    SlopeUpLeft => "Monte vers la gauche"; SlopeDirection::UpLeft, SlopeDirection::UpLeft
+]);
+
+impl_enum_ui_combo_wrapper!(SlopeAngleWrapper, SlopeAngleWrapperImStr, SlopeAngle, "angle"; [
+    SlopeSteep => "45°"; SlopeAngle::Steep, SlopeAngle::Steep,
+    SlopeGentle => "22.5°"; SlopeAngle::Gentle, SlopeAngle::Gentle
 ]);
 
 impl ImGuiEditor for PhysicsComponent {
@@ -256,11 +498,669 @@ impl ImGuiEditor for PhysicsComponent {
             self.body_type = b_t;
         }
 
+        if let Some(shape) = self.shape.draw_ui_combo(ui) {
+            self.shape = shape;
+        }
+
+        if let CollisionShape::Slope(ref mut direction, ref mut angle) = self.shape {
+            if let Some(d) = direction.draw_ui_combo(ui) {
+                *direction = d;
+            }
+            if let Some(a) = angle.draw_ui_combo(ui) {
+                *angle = a;
+            }
+        }
+
         match self.body_type {
             BodyType::Static => {}
-            BodyType::Dynamic { ref mut apply_gravity, ref mut jump_data } => {
+            BodyType::Dynamic { ref mut apply_gravity } => {
                 ui.checkbox(im_str!("Appliquer la gravité"), apply_gravity);
+
+                let movement = &mut self.movement;
+
+                let mut max_speed_x = movement.max_speed.x as f32;
+                let mut max_speed_y = movement.max_speed.y as f32;
+
+                if ui.drag_float(im_str!("vitesse max x"), &mut max_speed_x).min(0.).build() {
+                    movement.max_speed.x = max_speed_x as f64;
+                }
+                if ui.drag_float(im_str!("vitesse max y"), &mut max_speed_y).min(0.).build() {
+                    movement.max_speed.y = max_speed_y as f64;
+                }
+
+                let mut acceleration = movement.acceleration as f32;
+                let mut ground_friction = movement.ground_friction as f32;
+                let mut air_friction = movement.air_friction as f32;
+                let mut gravity_scale = movement.gravity_scale as f32;
+
+                if ui.drag_float(im_str!("accélération"), &mut acceleration).min(0.).build() {
+                    movement.acceleration = acceleration as f64;
+                }
+                if ui.drag_float(im_str!("friction au sol"), &mut ground_friction).min(0.).build() {
+                    movement.ground_friction = ground_friction as f64;
+                }
+                if ui.drag_float(im_str!("friction dans l'air"), &mut air_friction).min(0.).build() {
+                    movement.air_friction = air_friction as f64;
+                }
+                if ui.drag_float(im_str!("échelle de gravité"), &mut gravity_scale).speed(0.01).build() {
+                    movement.gravity_scale = gravity_scale as f64;
+                }
+
+                let jump = &mut self.jump;
+
+                let mut coyote_ticks = jump.coyote_ticks as i32;
+                let mut buffer_ticks = jump.buffer_ticks as i32;
+                let mut cut_factor = jump.cut_factor as f32;
+
+                if ui.slider_int(im_str!("temps coyote (ticks)"), &mut coyote_ticks, 0, 60).build() {
+                    jump.coyote_ticks = coyote_ticks as u32;
+                }
+                if ui.slider_int(im_str!("tampon de saut (ticks)"), &mut buffer_ticks, 0, 60).build() {
+                    jump.buffer_ticks = buffer_ticks as u32;
+                }
+                if ui.slider_float(im_str!("coupure du saut"), &mut cut_factor, 0., 1.).build() {
+                    jump.cut_factor = cut_factor as f64;
+                }
+
+                ui.text(format!("vitesse : ({:.1}, {:.1})", self.velocity.x, self.velocity.y));
+            }
+        }
+    }
+}
+
+impl_enum_ui_combo_wrapper!(PathModeWrapper, PathModeWrapperImStr, PathMode, "mode"; [
+    PathLoop => "Boucle"; PathMode::Loop, PathMode::Loop,
+    PathPingPong => "Aller-retour"; PathMode::PingPong, PathMode::PingPong,
+    PathOnce => "Une fois"; PathMode::Once, PathMode::Once
+]);
+
+impl ImGuiEditor for PathComponent {
+    fn draw_ui(&mut self, ui: &Ui) {
+        if let Some(m) = self.mode.draw_ui_combo(ui) {
+            self.mode = m;
+        }
+
+        let mut speed = self.speed as f32;
+        let mut wait_ticks = self.wait_ticks as i32;
+
+        if ui.drag_float(im_str!("vitesse"), &mut speed).min(0.).build() {
+            self.speed = speed as f64;
+        }
+        if ui.slider_int(im_str!("attente (ticks)"), &mut wait_ticks, 0, 1000).build() {
+            self.wait_ticks = wait_ticks as u32;
+        }
+
+        let mut remove_waypoint = None;
+
+        for (index, waypoint) in self.waypoints.iter().enumerate() {
+            ui.with_id(index as i32, || {
+                ui.text(format!("{} : ({:.0}, {:.0})", index, waypoint.x, waypoint.y));
+                ui.same_line(0.);
+                if ui.small_button(im_str!("Supprimer")) {
+                    remove_waypoint = Some(index);
+                }
+            });
+        }
+
+        if let Some(index) = remove_waypoint {
+            self.waypoints.remove(index);
+            self.reset();
+        }
+    }
+}
+
+impl ImGuiEditor for CheckpointComponent {
+    fn draw_ui(&mut self, ui: &Ui) {
+        let mut offset_x = self.spawn_offset.x as f32;
+        let mut offset_y = self.spawn_offset.y as f32;
+
+        if ui.drag_float(im_str!("réapparition x"), &mut offset_x).build() {
+            self.spawn_offset.x = offset_x as f64;
+        }
+        if ui.drag_float(im_str!("réapparition y"), &mut offset_y).build() {
+            self.spawn_offset.y = offset_y as f64;
+        }
+
+        ui.checkbox(im_str!("sauvegarder la physique"), &mut self.save_physics);
+    }
+}
+
+impl_enum_ui_combo_wrapper!(DeathBehaviorWrapper, DeathBehaviorWrapperImStr, DeathBehavior, "à la mort";
+[
+    DeathDelete => "Supprimer l'entité"; DeathBehavior::DeleteEntity, DeathBehavior::DeleteEntity,
+    DeathRespawn => "Réapparition"; DeathBehavior::Respawn, DeathBehavior::Respawn
+]);
+
+impl ImGuiEditor for HealthComponent {
+    fn draw_ui(&mut self, ui: &Ui) {
+        let mut max = self.max as i32;
+        let mut current = self.current as i32;
+        let mut invulnerability_ticks = self.invulnerability_ticks as i32;
+
+        if ui.slider_int(im_str!("vie max"), &mut max, 1, 100).build() {
+            self.max = max as u32;
+            self.current = self.current.min(self.max);
+        }
+        if ui.slider_int(im_str!("vie"), &mut current, 0, self.max as i32).build() {
+            self.current = current as u32;
+        }
+        if ui.slider_int(im_str!("invulnérabilité (ticks)"), &mut invulnerability_ticks, 0, 1000).build() {
+            self.invulnerability_ticks = invulnerability_ticks as u32;
+        }
+
+        if let Some(d) = self.on_death.draw_ui_combo(ui) {
+            self.on_death = d;
+        }
+
+        ui.text("Action à la mort :");
+        ui.same_line(0.);
+        self.death_action = draw_ui_action(self.death_action.clone(), im_str!("death action"), ui);
+    }
+}
+
+impl ImGuiEditor for DamageComponent {
+    fn draw_ui(&mut self, ui: &Ui) {
+        let mut amount = self.amount as i32;
+
+        if ui.slider_int(im_str!("dégâts"), &mut amount, 0, 100).build() {
+            self.amount = amount as u32;
+        }
+    }
+}
+
+impl_enum_ui_combo_wrapper!(CollectibleKindWrapper, CollectibleKindWrapperImStr, CollectibleKind, "type"; [
+    KindCoin => "Pièce"; CollectibleKind::Coin, CollectibleKind::Coin,
+    KindKey => "Clé"; CollectibleKind::Key(_), CollectibleKind::Key(0),
+    KindItem => "Objet"; CollectibleKind::Item, CollectibleKind::Item
+]);
+
+fn draw_ui_key(key: &mut u32, ui: &Ui) {
+    let mut key_i32 = *key as i32;
+
+    if ui.input_int(im_str!("clé"), &mut key_i32).build() {
+        *key = key_i32.max(0) as u32;
+    }
+}
+
+impl ImGuiEditor for CollectibleComponent {
+    fn draw_ui(&mut self, ui: &Ui) {
+        if let Some(k) = self.kind.draw_ui_combo(ui) {
+            self.kind = k;
+        }
+
+        if let CollectibleKind::Key(ref mut key) = self.kind {
+            draw_ui_key(key, ui);
+        }
+
+        let mut value = self.value as i32;
+
+        if ui.slider_int(im_str!("valeur"), &mut value, 0, 1000).build() {
+            self.value = value as u32;
+        }
+
+        ui.text("Au ramassage :");
+        ui.same_line(0.);
+        self.on_pickup = draw_ui_action(self.on_pickup.clone(), im_str!("pickup action"), ui);
+    }
+}
+
+impl ImGuiEditor for InventoryComponent {
+    fn draw_ui(&mut self, ui: &Ui) {
+        ui.text(format!("Pièces : {}", self.coins));
+        ui.text(format!("Objets : {}", self.items));
+        ui.text(format!("Clés : {:?}", self.keys));
+
+        if ui.button(im_str!("Vider"), (-1., 0.)) {
+            *self = InventoryComponent::default();
+        }
+    }
+}
+
+impl ImGuiEditor for DoorComponent {
+    fn draw_ui(&mut self, ui: &Ui) {
+        draw_ui_key(&mut self.key, ui);
+
+        ui.checkbox(im_str!("consommer la clé"), &mut self.consume_key);
+    }
+}
+
+impl_enum_ui_combo_wrapper!(AiBehaviorWrapper, AiBehaviorWrapperImStr, AiBehavior, "comportement"; [
+    BehaviorPatrol => "Patrouille"; AiBehavior::Patrol, AiBehavior::Patrol,
+    BehaviorChase => "Poursuite"; AiBehavior::Chase { .. }, AiBehavior::Chase { range: 300. }
+]);
+
+impl ImGuiEditor for AiComponent {
+    fn draw_ui(&mut self, ui: &Ui) {
+        if let Some(b) = self.behavior.draw_ui_combo(ui) {
+            self.behavior = b;
+        }
+
+        if let AiBehavior::Chase { ref mut range } = self.behavior {
+            let mut range_f32 = *range as f32;
+            if ui.drag_float(im_str!("portée"), &mut range_f32).min(0.).build() {
+                *range = range_f32 as f64;
+            }
+        }
+
+        let mut speed = self.speed as f32;
+        if ui.drag_float(im_str!("vitesse"), &mut speed).min(0.).build() {
+            self.speed = speed as f64;
+        }
+
+        ui.checkbox(im_str!("sauter les obstacles"), &mut self.jump_obstacles);
+
+        let mut jump_height = self.jump_height as i32;
+        if ui.slider_int(im_str!("hauteur de saut"), &mut jump_height, 0, 1000).build() {
+            self.jump_height = jump_height as u32;
+        }
+    }
+}
+
+impl_enum_ui_combo_wrapper!(VariableValueWrapper, VariableValueWrapperImStr, VariableValue, "type"; [
+    ValueInt => "Entier"; VariableValue::Int(_), VariableValue::Int(0),
+    ValueFloat => "Flottant"; VariableValue::Float(_), VariableValue::Float(0.),
+    ValueBool => "Booléen"; VariableValue::Bool(_), VariableValue::Bool(false),
+    ValueStr => "Texte"; VariableValue::Str(_), VariableValue::Str(String::new())
+]);
+
+fn draw_ui_string(label: &ImStr, value: &mut String, ui: &Ui) -> bool {
+    let mut buf = ImString::with_capacity(64);
+    buf.push_str(value);
+
+    if ui.input_text(label, &mut buf).build() {
+        let new_value: &str = buf.as_ref();
+        *value = new_value.to_owned();
+        return true;
+    }
+
+    false
+}
+
+fn draw_ui_variable_value(value: &mut VariableValue, ui: &Ui) {
+    if let Some(v) = value.draw_ui_combo(ui) {
+        *value = v;
+    }
+
+    match *value {
+        VariableValue::Int(ref mut i) => {
+            let mut i_i32 = *i as i32;
+            if ui.input_int(im_str!("valeur"), &mut i_i32).build() {
+                *i = i_i32 as i64;
+            }
+        }
+        VariableValue::Float(ref mut f) => {
+            let mut f_f32 = *f as f32;
+            if ui.input_float(im_str!("valeur"), &mut f_f32).build() {
+                *f = f_f32 as f64;
+            }
+        }
+        VariableValue::Bool(ref mut b) => {
+            ui.checkbox(im_str!("valeur"), b);
+        }
+        VariableValue::Str(ref mut s) => {
+            draw_ui_string(im_str!("valeur"), s, ui);
+        }
+    }
+}
+
+impl ImGuiEditor for VariablesComponent {
+    fn draw_ui(&mut self, ui: &Ui) {
+        let mut rename = None;
+        let mut remove = None;
+
+        ui.columns(3, im_str!("variables"), true);
+        ui.text("Nom");
+        ui.next_column();
+        ui.text("Valeur");
+        ui.next_column();
+        ui.next_column();
+        ui.separator();
+
+        for (index, (name, value)) in self.vars.iter_mut().enumerate() {
+            ui.with_id(index as i32, || {
+                let mut new_name = name.clone();
+                if draw_ui_string(im_str!("nom"), &mut new_name, ui) {
+                    rename = Some((name.clone(), new_name));
+                }
+                ui.next_column();
+
+                draw_ui_variable_value(value, ui);
+                ui.next_column();
+
+                if ui.small_button(im_str!("Supprimer")) {
+                    remove = Some(name.clone());
+                }
+                ui.next_column();
+            });
+        }
+
+        ui.columns(1, im_str!("variables"), false);
+
+        if let Some((old_name, new_name)) = rename {
+            if !new_name.is_empty() && !self.vars.contains_key(&new_name) {
+                if let Some(value) = self.vars.remove(&old_name) {
+                    self.vars.insert(new_name, value);
+                }
             }
         }
+
+        if let Some(name) = remove {
+            self.vars.remove(&name);
+        }
+
+        if ui.button(im_str!("Ajouter"), (-1., 0.)) {
+            let name = (0..).map(|i| format!("variable{}", i)).find(|n| !self.vars.contains_key(n)).unwrap();
+            self.vars.insert(name, VariableValue::Int(0));
+        }
     }
-}
\ No newline at end of file
+}
+
+impl ImGuiEditor for ScriptComponent {
+    fn draw_ui(&mut self, ui: &Ui) {
+        let mut file = self.file.to_string_lossy().into_owned();
+
+        if draw_ui_string(im_str!("fichier"), &mut file, ui) {
+            self.file = file.into();
+        }
+
+        ui.text_wrapped(im_str!("Fonctions appelées : on_update(id), on_collide(id, other), on_input(id, key, just_pressed), on_trigger(id, other)"));
+    }
+}
+
+impl_enum_ui_combo_wrapper!(FrameSourceWrapper, FrameSourceWrapperImStr, FrameSource, "source"; [
+    SourceSpriteSheet => "Planche de sprites"; FrameSource::SpriteSheet { .. }, FrameSource::SpriteSheet { image: SpriteImage::new_unloaded(PathBuf::new()), columns: NonZeroU32::new(1).unwrap(), rows: NonZeroU32::new(1).unwrap() },
+    SourceImages => "Images séparées"; FrameSource::Images(_), FrameSource::Images(vec![])
+]);
+
+impl_enum_ui_combo_wrapper!(LoopModeWrapper, LoopModeWrapperImStr, LoopMode, "lecture"; [
+    LoopLoop => "En boucle"; LoopMode::Loop, LoopMode::Loop,
+    LoopOnce => "Une fois"; LoopMode::Once, LoopMode::Once,
+    LoopPingPong => "Aller-retour"; LoopMode::PingPong, LoopMode::PingPong
+]);
+
+fn draw_ui_sprite_image(label: &ImStr, image: &mut SpriteImage, ui: &Ui) {
+    let mut path = image.get_path().to_string_lossy().into_owned();
+
+    // L'image est chargée par le `LoadingResourcesSystem` au prochain tick
+    if draw_ui_string(label, &mut path, ui) {
+        *image = SpriteImage::new_unloaded(path.into());
+    }
+}
+
+fn draw_ui_non_zero_u32(label: &ImStr, value: &mut NonZeroU32, ui: &Ui) {
+    let mut value_i32 = value.get() as i32;
+    if ui.drag_int(label, &mut value_i32).build() && value_i32 > 0 {
+        *value = NonZeroU32::new(value_i32 as u32).unwrap();
+    }
+}
+
+impl ImGuiEditor for AnimationComponent {
+    fn draw_ui(&mut self, ui: &Ui) {
+        draw_ui_render_layer(&mut self.layer, &mut self.z_index, ui);
+
+        if let Some(s) = self.source.draw_ui_combo(ui) {
+            self.source = s;
+        }
+
+        match self.source {
+            FrameSource::SpriteSheet { ref mut image, ref mut columns, ref mut rows } => {
+                draw_ui_sprite_image(im_str!("image"), image, ui);
+                draw_ui_non_zero_u32(im_str!("colonnes"), columns, ui);
+                draw_ui_non_zero_u32(im_str!("lignes"), rows, ui);
+            }
+            FrameSource::Images(ref mut images) => {
+                let mut remove = None;
+
+                for (index, image) in images.iter_mut().enumerate() {
+                    ui.with_id(index as i32, || {
+                        draw_ui_sprite_image(im_str!("image"), image, ui);
+                        ui.same_line(0.);
+                        if ui.small_button(im_str!("Supprimer")) {
+                            remove = Some(index);
+                        }
+                    });
+                }
+
+                if let Some(index) = remove {
+                    images.remove(index);
+                }
+
+                if ui.button(im_str!("Ajouter une image"), (-1., 0.)) {
+                    images.push(SpriteImage::new_unloaded(PathBuf::new()));
+                }
+            }
+        }
+
+        ui.separator();
+
+        let frame_count = self.source.frame_count();
+        let states = [
+            (AnimationState::Idle, im_str!("Repos")),
+            (AnimationState::Run, im_str!("Course")),
+            (AnimationState::Jump, im_str!("Saut")),
+            (AnimationState::Fall, im_str!("Chute")),
+        ];
+
+        for (state, name) in states.iter() {
+            ui.with_id(*state as i32, || {
+                let mut used = self.clips.contains_key(state);
+                if ui.checkbox(name, &mut used) {
+                    if used {
+                        self.clips.insert(*state, AnimationClip::default());
+                    } else {
+                        self.clips.remove(state);
+                    }
+                }
+
+                if let Some(clip) = self.clips.get_mut(state) {
+                    draw_ui_u32(im_str!("première image"), &mut clip.first_frame, frame_count.saturating_sub(1) as i32, ui);
+                    draw_ui_u32(im_str!("nombre d'images"), &mut clip.frame_count, frame_count.max(1) as i32, ui);
+                    draw_ui_u32(im_str!("durée d'une image (ticks)"), &mut clip.frame_ticks, 120, ui);
+
+                    if let Some(m) = clip.loop_mode.draw_ui_combo(ui) {
+                        clip.loop_mode = m;
+                    }
+                }
+            });
+        }
+
+        ui.separator();
+
+        let mut preview = self.preview.map_or(0, |p| states.iter().position(|s| s.0 == p).unwrap() as i32 + 1);
+        let names: Vec<&ImStr> = Some(im_str!("Automatique")).into_iter().chain(states.iter().map(|s| s.1)).collect();
+
+        if ui.combo(im_str!("aperçu"), &mut preview, &names, 10) {
+            self.preview = if preview > 0 { Some(states[preview as usize - 1].0) } else { None };
+        }
+
+        ui.text(format!("Image affichée : {}/{}", self.current_frame(), frame_count));
+    }
+}
+
+impl_enum_ui_combo_wrapper!(LightShapeWrapper, LightShapeWrapperImStr, LightShape, "forme"; [
+    LightPoint => "Ponctuelle"; LightShape::Point, LightShape::Point,
+    LightCone => "Cône"; LightShape::Cone { .. }, LightShape::Cone { direction: 0., angle: 60. }
+]);
+
+impl ImGuiEditor for LightComponent {
+    fn draw_ui(&mut self, ui: &Ui) {
+        if let Some(shape) = self.shape.draw_ui_combo(ui) {
+            self.shape = shape;
+        }
+
+        if let LightShape::Cone { ref mut direction, ref mut angle } = self.shape {
+            let mut direction_f32 = *direction as f32;
+            if ui.slider_float(im_str!("direction"), &mut direction_f32, -180., 180.).build() {
+                *direction = direction_f32 as f64;
+            }
+
+            let mut angle_f32 = *angle as f32;
+            if ui.slider_float(im_str!("ouverture"), &mut angle_f32, 1., 360.).build() {
+                *angle = angle_f32 as f64;
+            }
+        }
+
+        let mut radius = self.radius as f32;
+        if ui.drag_float(im_str!("rayon"), &mut radius).min(0.).build() {
+            self.radius = radius as f64;
+        }
+
+        let mut editable_color = [self.color.r, self.color.g, self.color.b];
+        if ui.color_edit(im_str!("couleur"), EditableColor::Float3(&mut editable_color)).build() {
+            self.color.r = editable_color[0];
+            self.color.g = editable_color[1];
+            self.color.b = editable_color[2];
+        }
+
+        let mut intensity = self.intensity as f32;
+        if ui.drag_float(im_str!("intensité"), &mut intensity).min(0.).speed(0.01).build() {
+            self.intensity = intensity as f64;
+        }
+
+        let mut flicker = self.flicker as f32;
+        if ui.slider_float(im_str!("scintillement"), &mut flicker, 0., 1.).build() {
+            self.flicker = flicker as f64;
+        }
+    }
+}
+
+fn draw_ui_particle_color(label: &ImStr, color: &mut Color, ui: &Ui) {
+    let mut editable_color = [color.r, color.g, color.b, color.a];
+    if ui.color_edit(label, EditableColor::Float4(&mut editable_color)).build() {
+        *color = Color::new(editable_color[0], editable_color[1], editable_color[2], editable_color[3]);
+    }
+}
+
+fn draw_ui_particle_settings(settings: &mut ParticleSettings, ui: &Ui) {
+    let mut lifetime = settings.lifetime as f32;
+    if ui.drag_float(im_str!("durée de vie (s)"), &mut lifetime).min(0.01).speed(0.01).build() {
+        settings.lifetime = lifetime as f64;
+    }
+
+    let mut direction = settings.direction as f32;
+    if ui.slider_float(im_str!("direction"), &mut direction, -180., 180.).build() {
+        settings.direction = direction as f64;
+    }
+
+    let mut spread = settings.spread as f32;
+    if ui.slider_float(im_str!("ouverture"), &mut spread, 0., 360.).build() {
+        settings.spread = spread as f64;
+    }
+
+    let mut speed = [settings.min_speed as f32, settings.max_speed as f32];
+    if ui.drag_float(im_str!("vitesse min"), &mut speed[0]).min(0.).build() {
+        settings.min_speed = speed[0] as f64;
+        settings.max_speed = settings.max_speed.max(settings.min_speed);
+    }
+    if ui.drag_float(im_str!("vitesse max"), &mut speed[1]).min(0.).build() {
+        settings.max_speed = speed[1] as f64;
+        settings.min_speed = settings.min_speed.min(settings.max_speed);
+    }
+
+    let mut gravity = settings.gravity as f32;
+    if ui.drag_float(im_str!("gravité"), &mut gravity).build() {
+        settings.gravity = gravity as f64;
+    }
+
+    draw_ui_particle_color(im_str!("couleur initiale"), &mut settings.start_color, ui);
+    draw_ui_particle_color(im_str!("couleur finale"), &mut settings.end_color, ui);
+
+    let mut size = [settings.start_size as f32, settings.end_size as f32];
+    if ui.drag_float(im_str!("taille initiale"), &mut size[0]).min(0.).build() {
+        settings.start_size = size[0] as f64;
+    }
+    if ui.drag_float(im_str!("taille finale"), &mut size[1]).min(0.).build() {
+        settings.end_size = size[1] as f64;
+    }
+
+    // Un chemin vide revient à la texture par défaut
+    let mut texture = settings.texture.as_ref().map_or(String::new(), |path| path.to_string_lossy().into_owned());
+    if draw_ui_string(im_str!("texture"), &mut texture, ui) {
+        settings.texture = if texture.is_empty() { None } else { Some(texture.into()) };
+    }
+}
+
+impl ImGuiEditor for ParticleEmitterComponent {
+    fn draw_ui(&mut self, ui: &Ui) {
+        ui.checkbox(im_str!("actif"), &mut self.enabled);
+
+        let mut rate = self.rate as f32;
+        if ui.drag_float(im_str!("particules par seconde"), &mut rate).min(0.).build() {
+            self.rate = rate as f64;
+        }
+
+        ui.separator();
+
+        draw_ui_particle_settings(&mut self.settings, ui);
+
+        ui.text(format!("Particules : {}", self.get_particles().len()));
+    }
+}
+
+fn draw_ui_marker(marker: &mut Option<U64Marker>, ui: &Ui) {
+    let mut id = marker.map_or(-1, |m| m.id() as i32);
+
+    if ui.input_int(im_str!("marqueur"), &mut id).build() {
+        *marker = if id >= 0 { Some(U64Marker(id as u64)) } else { None };
+    }
+}
+
+impl_enum_ui_combo_wrapper!(TriggerFilterWrapper, TriggerFilterWrapperImStr, TriggerFilter, "filtre"; [
+    FilterAny => "Toutes les entités"; TriggerFilter::Any, TriggerFilter::Any,
+    FilterWithInput => "Entités contrôlées"; TriggerFilter::WithInput, TriggerFilter::WithInput,
+    FilterMarked => "Entité marquée"; TriggerFilter::Marked(_), TriggerFilter::Marked(None)
+]);
+
+impl_enum_ui_combo_wrapper!(TriggerTargetWrapper, TriggerTargetWrapperImStr, TriggerTarget, "cible"; [
+    TargetTrigger => "Zone"; TriggerTarget::Trigger, TriggerTarget::Trigger,
+    TargetOther => "Entité détectée"; TriggerTarget::Other, TriggerTarget::Other,
+    TargetMarked => "Entité marquée"; TriggerTarget::Marked(_), TriggerTarget::Marked(None)
+]);
+
+fn draw_ui_trigger_actions(label: &ImStr, trigger_actions: &mut Vec<(TriggerTarget, Actions)>, ui: &Ui) {
+    ui.tree_node(label).build(|| {
+        let mut remove_action = None;
+
+        for (index, (target, action)) in trigger_actions.iter_mut().enumerate() {
+            ui.with_id(index as i32, || {
+                if let Some(t) = target.draw_ui_combo(ui) {
+                    *target = t;
+                }
+
+                if let TriggerTarget::Marked(ref mut marker) = *target {
+                    draw_ui_marker(marker, ui);
+                }
+
+                *action = draw_ui_action(action.clone(), im_str!("action {}", index), ui);
+                ui.same_line(0.);
+                if ui.small_button(im_str!("Supprimer")) {
+                    remove_action = Some(index);
+                }
+            });
+        }
+
+        if let Some(index) = remove_action {
+            trigger_actions.remove(index);
+        }
+
+        if ui.button(im_str!("Ajouter"), (-1., 0.)) {
+            trigger_actions.push((TriggerTarget::Other, Actions::Empty));
+        }
+    });
+}
+
+impl ImGuiEditor for TriggerComponent {
+    fn draw_ui(&mut self, ui: &Ui) {
+        if let Some(f) = self.filter.draw_ui_combo(ui) {
+            self.filter = f;
+        }
+
+        if let TriggerFilter::Marked(ref mut marker) = self.filter {
+            draw_ui_marker(marker, ui);
+        }
+
+        draw_ui_trigger_actions(im_str!("Entrée dans la zone"), &mut self.on_enter, ui);
+        draw_ui_trigger_actions(im_str!("Présence dans la zone"), &mut self.on_stay, ui);
+        draw_ui_trigger_actions(im_str!("Sortie de la zone"), &mut self.on_exit, ui);
+    }
+}
@@ -40,16 +40,13 @@ impl<'a> System<'a> for InputSystem {
             for (key, just_pressed, action) in input.input_actions.iter() {
                 if let Some(jp) = self.input_manager.lock().unwrap().is_key_pressed(Keycode::from_i32(*key).expect(&format!("Touche {} non reconnue !", key))) {
                     if jp == *just_pressed {
-                        let mut inserted = false;
-                        {
-                            if let Some(a) = actions.get_mut(ent) {
-                                a.actions_remaining.push(action.clone());
-                                inserted = true;
-                            }
-                        }
+                        // La touche envoie encore des actions tant qu'elle est maintenue, ses sauts sont donc variables
+                        let held = input.input_actions.iter().any(|(held_key, held_jp, _)| held_key == key && !held_jp);
 
-                        if !inserted {
-                            actions.insert(ent, ActionComponent { actions_remaining: vec![action.clone()] }).unwrap();
+                        if held {
+                            ActionComponent::push_held_input_action(&mut actions, ent, action.clone());
+                        } else {
+                            ActionComponent::push_action(&mut actions, ent, action.clone());
                         }
                     }
                 }
@@ -1,23 +1,60 @@
 use crate::{
     ecs::{
         self,
+        actions::{
+            ActionComponent,
+            Actions,
+        },
+        audio::{
+            self,
+            MusicConfig,
+            SoundQueue,
+        },
+        checkpoints::RespawnPoint,
+        collectibles::{
+            InventoryComponent,
+            Score,
+        },
         chunk::{
             ActiveChunksRect,
+            ChunksGrid,
             ChunkSystem,
         },
+        goals::{
+            LevelGoals,
+            LevelProgress,
+            LevelStatus,
+        },
+        lighting::{
+            LightingConfig,
+            LightingRenderSystem,
+            LightRenderer,
+        },
         loading::LoadingResourcesSystem,
+        particles::ParticleBursts,
         render::{
+            self,
+            ParallaxConfig,
             RenderSystem,
             SpriteImage
         },
+        scripting::Scripting,
         serialization::{
-            DeserializeSystem,
-            SerializeSystem,
+            self,
+            WorldEncoding,
+            WorldError,
+            WorldMetadata,
         },
     },
     utils::{
+        audio::{
+            AudioBackend,
+            GgezAudioBackend,
+            NullAudioBackend,
+        },
         camera::Camera,
         constants,
+        math::Rect,
         resources_manager::ResourcesManager,
         serde::ColorDef,
     },
@@ -81,13 +118,33 @@ impl Default for FollowEntity {
     }
 }
 
+/// Durée du tick en cours, en secondes.
+pub struct DeltaTime(pub f64);
+
+impl Default for DeltaTime {
+    fn default() -> Self {
+        DeltaTime(1. / constants::DESIRED_FPS as f64)
+    }
+}
+
 #[derive(Serialize, Deserialize, Clone, Debug)]
 pub struct LevelConfig {
     pub author: String,
     pub name: String,
     pub background: Background,
     pub dir: PathBuf,
-    pub initial_follow_entity: FollowEntity
+    pub initial_follow_entity: FollowEntity,
+    #[serde(default)]
+    pub goals: LevelGoals,
+    #[serde(default)]
+    pub parallax: ParallaxConfig,
+    #[serde(default)]
+    pub lighting: LightingConfig,
+    #[serde(default)]
+    pub music: Option<MusicConfig>,
+    /// Encodage du monde chargé par le jeu. Le RON est toujours enregistré dans `world.dat`, le binaire l'est en plus dans `world.bin`
+    #[serde(default)]
+    pub world_encoding: WorldEncoding,
 }
 
 impl LevelConfig {
@@ -104,8 +161,31 @@ impl LevelConfig {
         config_file.write_all(content.as_bytes()).expect("Impossible d'écrire la configuration du niveau dans le fichier !");
     }
 
-    fn world_data_path(&self) -> PathBuf {
-        self.dir.join(constants::path::LEVEL_WORLD_DATA_FILE.as_path())
+    /// Convertit le monde dans l'encodage donné et l'enregistre dans la configuration.
+    /// Le fichier converti est écrit à côté de l'original, le RON et le binaire peuvent donc coexister.
+    pub fn convert_world(&mut self, encoding: WorldEncoding) -> Result<(), WorldError> {
+        let content = fs::read(self.world_data_path(self.world_encoding))?;
+
+        // Converti en mémoire, le fichier d'origine reste intact si la conversion échoue
+        let mut converted = vec![];
+        serialization::convert_world(content.as_slice(), &self.name, &self.author, encoding, &mut converted)?;
+        fs::write(self.world_data_path(encoding), converted)?;
+
+        self.world_encoding = encoding;
+        self.save();
+
+        Ok(())
+    }
+
+    fn world_data_path(&self, encoding: WorldEncoding) -> PathBuf {
+        match encoding {
+            WorldEncoding::Ron => self.dir.join(constants::path::LEVEL_WORLD_DATA_FILE.as_path()),
+            WorldEncoding::Binary => self.dir.join(constants::path::LEVEL_WORLD_BINARY_FILE.as_path()),
+        }
+    }
+
+    pub fn replay_path(&self) -> PathBuf {
+        self.dir.join(constants::path::LEVEL_REPLAY_FILE.as_path())
     }
 }
 
@@ -115,7 +195,17 @@ pub struct Level<'a, 'b> {
     dispatcher: Dispatcher<'a, 'b>,
     chunk_sys: ChunkSystem,
     resources_manager: ResourcesManager,
+    /// Composition de l'éclairage avec l'écran, `None` si le niveau n'est pas éclairé
     blend_mode: Option<BlendMode>,
+    light_renderer: LightRenderer,
+    /// Désactivée dans l'éditeur pour que les entités restent sous la souris
+    parallax_enabled: bool,
+    audio: Box<dyn AudioBackend>,
+    /// Version du format de `world.dat` lors du chargement, le fichier est converti au prochain enregistrement
+    world_version: u32,
+    world_metadata: WorldMetadata,
+    /// Les ressources sont rechargées au prochain tick, après une modification depuis l'éditeur
+    resources_dirty: bool,
 }
 
 pub fn clone<'l, 'l2, F: FnMut(DispatcherBuilder<'l, 'l2>) -> DispatcherBuilder<'l, 'l2>>(level: &Level, mut build_dispatcher: F) -> Level<'l, 'l2> {
@@ -127,29 +217,81 @@ pub fn clone<'l, 'l2, F: FnMut(DispatcherBuilder<'l, 'l2>) -> DispatcherBuilder<
 
     dispatcher.setup(&mut world.res);
 
-    Level {
+    let mut level = Level {
         config: level.config.clone(),
         world,
         dispatcher,
         chunk_sys,
         resources_manager: level.resources_manager.clone(),
         blend_mode: level.blend_mode.clone(),
-    }
+        light_renderer: LightRenderer::default(),
+        parallax_enabled: true,
+        audio: Box::new(NullAudioBackend),
+        world_version: level.world_version,
+        world_metadata: level.world_metadata.clone(),
+        resources_dirty: false,
+    };
+
+    level.set_audio_backend(Box::new(GgezAudioBackend::default()));
+
+    level
 }
 
 impl<'a, 'b> Level<'a, 'b> {
-    pub fn load<F: FnMut(DispatcherBuilder<'a, 'b>) -> DispatcherBuilder<'a, 'b>>(ctx: &mut Context, config: LevelConfig, resources_manager: Option<ResourcesManager>, build_dispatcher: F) -> Self {
+    pub fn load<F: FnMut(DispatcherBuilder<'a, 'b>) -> DispatcherBuilder<'a, 'b>>(ctx: &mut Context, config: LevelConfig, resources_manager: Option<ResourcesManager>, build_dispatcher: F) -> Result<Self, WorldError> {
+        let mut level = Self::load_headless(config, build_dispatcher)?;
+
+        level.resources_manager = resources_manager.unwrap_or_default();
+
+        LoadingResourcesSystem { ctx, resources_manager: &mut level.resources_manager }.run_now(&level.world.res);
+        level.resources_manager.build_atlas(ctx);
+        level.load_background(ctx);
+
+        level.set_audio_backend(Box::new(GgezAudioBackend::default()));
+
+        Ok(level)
+    }
+
+    /// Charge le niveau sans contexte graphique : les sprites restent non chargés, le niveau ne peut pas être dessiné et reste silencieux.
+    pub fn load_headless<F: FnMut(DispatcherBuilder<'a, 'b>) -> DispatcherBuilder<'a, 'b>>(config: LevelConfig, build_dispatcher: F) -> Result<Self, WorldError> {
         let (mut world, dispatcher, chunk_sys) = Self::build_default_world(build_dispatcher);
 
         world.write_resource::<FollowEntity>().0 = config.initial_follow_entity.0.clone();
+        *world.write_resource::<LevelGoals>() = config.goals.clone();
+        world.write_resource::<Scripting>().dir = config.dir.clone();
+
+        let (world_version, world_metadata) = Self::load_world_data(&world, &config)?;
+
+        let blend_mode = Self::lighting_blend_mode(&config.lighting);
+
+        Ok(Level {
+            config,
+            world,
+            dispatcher,
+            chunk_sys,
+            resources_manager: ResourcesManager::default(),
+            blend_mode,
+            light_renderer: LightRenderer::default(),
+            parallax_enabled: true,
+            audio: Box::new(NullAudioBackend),
+            world_version,
+            world_metadata,
+            resources_dirty: false,
+        })
+    }
 
-        let mut resources_manager = resources_manager.unwrap_or_default();
-
-        DeserializeSystem { reader: File::open(&config.world_data_path()).unwrap() }.run_now(&world.res);
+    /// Le binaire dépend de la disposition exacte des composants : s'il est illisible, le RON enregistré à côté est chargé à la place.
+    fn load_world_data(world: &World, config: &LevelConfig) -> Result<(u32, WorldMetadata), WorldError> {
+        if config.world_encoding == WorldEncoding::Binary {
+            let ron_path = config.world_data_path(WorldEncoding::Ron);
 
-        LoadingResourcesSystem { ctx, resources_manager: &mut resources_manager }.run_now(&world.res);
+            match File::open(config.world_data_path(WorldEncoding::Binary)).map_err(WorldError::from).and_then(|file| serialization::load_world(world, file)) {
+                Err(ref err) if ron_path.exists() => eprintln!("Monde binaire du niveau \"{}\" illisible, chargement du RON : {}", config.name, err),
+                result => return result,
+            }
+        }
 
-        Level { config, world, dispatcher, chunk_sys, resources_manager, blend_mode: None }
+        serialization::load_world(world, File::open(config.world_data_path(WorldEncoding::Ron))?)
     }
 
     pub fn new<F: FnMut(DispatcherBuilder<'a, 'b>) -> DispatcherBuilder<'a, 'b>, W: FnMut(&mut World) -> ()>(ctx: &mut Context, author: String, name: String, build_dispatcher: F, mut populate_world: W) -> Self {
@@ -160,6 +302,7 @@ impl<'a, 'b> Level<'a, 'b> {
         populate_world(&mut world);
 
         LoadingResourcesSystem { ctx, resources_manager: &mut resources_manager }.run_now(&world.res);
+        resources_manager.build_atlas(ctx);
 
         let dir = constants::path::LEVELS_DIR.join(Path::new(&name));
 
@@ -168,16 +311,110 @@ impl<'a, 'b> Level<'a, 'b> {
             name,
             background: Background::default(),
             dir,
-            initial_follow_entity: FollowEntity::default()
+            initial_follow_entity: FollowEntity::default(),
+            goals: LevelGoals::default(),
+            parallax: ParallaxConfig::default(),
+            lighting: LightingConfig::default(),
+            music: None,
+            world_encoding: WorldEncoding::default(),
         };
 
-        Level { config, world, dispatcher, chunk_sys, resources_manager, blend_mode: None }
+        world.write_resource::<Scripting>().dir = config.dir.clone();
+
+        let blend_mode = Self::lighting_blend_mode(&config.lighting);
+
+        Level {
+            config,
+            world,
+            dispatcher,
+            chunk_sys,
+            resources_manager,
+            blend_mode,
+            light_renderer: LightRenderer::default(),
+            parallax_enabled: true,
+            audio: Box::new(GgezAudioBackend::default()),
+            world_version: serialization::WORLD_FORMAT_VERSION,
+            world_metadata: WorldMetadata::default(),
+            resources_dirty: false,
+        }
+    }
+
+    /// Charge les images ajoutées ou modifiées depuis le chargement du niveau.
+    fn load_resources(&mut self, ctx: &mut Context) {
+        LoadingResourcesSystem { ctx, resources_manager: &mut self.resources_manager }.run_now(&self.world.res);
+        self.load_background(ctx);
+    }
+
+    /// Demande le chargement des images au prochain tick, à appeler lorsque l'éditeur modifie une image.
+    pub fn reload_resources(&mut self) {
+        self.resources_dirty = true;
+    }
+
+    /// Vrai si une rafale de particules utilise une texture qui n'a pas encore été chargée.
+    fn has_unloaded_burst_textures(&self) -> bool {
+        self.world.read_resource::<ParticleBursts>().textures().any(|path| self.resources_manager.get_texture(path).is_none() && !self.resources_manager.has_failed(path))
+    }
+
+    fn load_background(&mut self, ctx: &mut Context) {
+        if let Background::Texture(ref mut spr_image, _) = self.config.background {
+            if spr_image.is_loaded() || self.resources_manager.has_failed(spr_image.get_path()) {
+                return;
+            }
+
+            match self.resources_manager.load_or_get_texture(ctx, spr_image.get_path()) {
+                Ok(Some(image)) => {
+                    let image = image.clone();
+                    spr_image.load_after_deserialization(|_| image);
+                }
+                Ok(None) => {}
+                Err(err) => eprintln!("Impossible de charger l'arrière plan {:?} : {}", spr_image.get_path(), err),
+            }
+        }
+    }
+
+    pub fn get_config(&self) -> &LevelConfig { &self.config }
+
+    pub fn get_world_version(&self) -> u32 { self.world_version }
+
+    pub fn get_world_metadata(&self) -> &WorldMetadata { &self.world_metadata }
+
+    pub fn get_progress(&self) -> LevelProgress { self.world.read_resource::<LevelProgress>().clone() }
+
+    fn get_followed_entity(&self) -> Option<Entity> {
+        self.world.read_resource::<FollowEntity>().0.and_then(|m| self.world.read_resource::<U64MarkerAllocator>().retrieve_entity_internal(m.id()))
+    }
+
+    /// L'entité suivie existe encore et un point de réapparition est connu.
+    pub fn can_respawn(&self) -> bool {
+        self.get_followed_entity().map_or(false, |e| self.world.is_alive(e)) && self.world.read_resource::<RespawnPoint>().pos.is_some()
+    }
+
+    /// Reprend la partie au dernier point de contrôle, l'entité suivie y est ramenée au prochain tick.
+    pub fn respawn(&mut self) {
+        if !self.can_respawn() {
+            return;
+        }
+
+        if let Some(followed) = self.get_followed_entity() {
+            ActionComponent::push_action(&mut self.world.write_storage::<ActionComponent>(), followed, Actions::Respawn);
+        }
+
+        let mut progress = self.world.write_resource::<LevelProgress>();
+        progress.status = LevelStatus::Playing;
+        progress.dead = false;
+    }
+
+    pub fn get_score(&self) -> u32 { self.world.read_resource::<Score>().0 }
+
+    /// Inventaire de l'entité suivie, s'il y en a un.
+    pub fn get_followed_inventory(&self) -> Option<InventoryComponent> {
+        self.get_followed_entity().and_then(|e| self.world.read_storage::<InventoryComponent>().get(e).cloned())
     }
 
     pub fn get_world(&self) -> &World { &self.world }
     pub fn get_world_mut(&mut self) -> &mut World { &mut self.world }
 
-    pub fn get_chunk_sys(&self) -> &ChunkSystem { &self.chunk_sys }
+    pub fn get_chunks_bounds(&self) -> Rect { self.world.read_resource::<ChunksGrid>().get_bounds_chunks() }
 
     fn build_default_world<F: FnMut(DispatcherBuilder<'a, 'b>) -> DispatcherBuilder<'a, 'b>>(mut build_dispatcher: F) -> (World, Dispatcher<'a, 'b>, ChunkSystem) {
         let (mut world, chunk_sys) = ecs::create_default_world();
@@ -205,10 +442,72 @@ impl<'a, 'b> Level<'a, 'b> {
         }
     }
 
-    pub fn draw(&self, ctx: &mut Context, camera: &Camera) {
+    pub fn background_mut(&mut self) -> &mut Background { &mut self.config.background }
+
+    pub fn parallax_mut(&mut self) -> &mut ParallaxConfig { &mut self.config.parallax }
+
+    pub fn is_parallax_enabled(&self) -> bool { self.parallax_enabled }
+
+    pub fn set_parallax_enabled(&mut self, enabled: bool) {
+        self.parallax_enabled = enabled;
+    }
+
+    fn lighting_blend_mode(lighting: &LightingConfig) -> Option<BlendMode> {
+        if lighting.enabled { Some(BlendMode::Multiply) } else { None }
+    }
+
+    pub fn is_lighting_enabled(&self) -> bool { self.config.lighting.enabled }
+
+    pub fn set_lighting_enabled(&mut self, enabled: bool) {
+        self.config.lighting.enabled = enabled;
+        self.blend_mode = Self::lighting_blend_mode(&self.config.lighting);
+    }
+
+    pub fn ambient_light_mut(&mut self) -> &mut Color { &mut self.config.lighting.ambient }
+
+    pub fn music_mut(&mut self) -> &mut Option<MusicConfig> { &mut self.config.music }
+
+    pub fn world_encoding_mut(&mut self) -> &mut WorldEncoding { &mut self.config.world_encoding }
+
+    /// Remplace la sortie audio, la musique du niveau est relancée sur la nouvelle sortie.
+    pub fn set_audio_backend(&mut self, audio: Box<dyn AudioBackend>) {
+        self.audio.stop_music();
+        self.audio = audio;
+        self.play_music();
+    }
+
+    /// Joue la musique du niveau depuis le début.
+    pub fn play_music(&mut self) {
+        match self.config.music {
+            Some(ref music) if !music.path.as_os_str().is_empty() => self.audio.play_music(&music.path, music.looping, music.volume),
+            _ => self.audio.stop_music(),
+        }
+    }
+
+    pub fn stop_music(&mut self) {
+        self.audio.stop_music();
+    }
+
+    /// Transmet les sons demandés pendant le tick à la sortie audio, atténués selon leur distance à la caméra.
+    fn play_sounds(&mut self, camera: Option<&Camera>) {
+        for (path, volume, pos) in self.world.write_resource::<SoundQueue>().drain() {
+            let attenuation = match (camera, pos) {
+                (Some(camera), Some(pos)) => audio::attenuation(camera, pos),
+                _ => 1.,
+            };
+
+            if attenuation > 0. {
+                self.audio.play_sound(&path, volume * attenuation);
+            }
+        }
+    }
+
+    pub fn draw(&mut self, ctx: &mut Context, camera: &Camera) {
         let active_rect_chunk = self.world.read_resource::<ActiveChunksRect>().get_rect().clone();
 
-        if let Background::Texture(ref _path, ref _col) = self.config.background {}
+        if let Background::Texture(ref spr_image, _) = self.config.background {
+            render::draw_tiled_background(ctx, camera, spr_image, if self.parallax_enabled { self.config.parallax.texture } else { 1. });
+        }
 
         let rect_in_screen = camera.world_rect_to_screen(active_rect_chunk);
 
@@ -218,19 +517,33 @@ impl<'a, 'b> Level<'a, 'b> {
 
         graphics::set_color(ctx, (255, 255, 255, 255).into()).unwrap();
 
-        RenderSystem { ctx, camera }.run_now(&self.world.res);
+        let parallax = if self.parallax_enabled { Some(&self.config.parallax) } else { None };
+
+        RenderSystem { ctx, camera, resources_manager: &self.resources_manager, parallax }.run_now(&self.world.res);
+
+        if let Some(blend_mode) = self.blend_mode {
+            LightingRenderSystem { ctx, camera, renderer: &mut self.light_renderer, ambient: self.config.lighting.ambient, blend_mode }.run_now(&self.world.res);
+        }
     }
 
-    pub fn save(&mut self) {
+    pub fn save(&mut self) -> Result<(), WorldError> {
         if !self.config.dir.exists() {
-            fs::create_dir(&self.config.dir).unwrap();
+            fs::create_dir(&self.config.dir)?;
         }
 
-        SerializeSystem { writer: File::create(&self.config.world_data_path()).unwrap() }.run_now(&self.world.res);
+        self.world_metadata = serialization::save_world(&self.world, &self.config.name, &self.config.author, WorldEncoding::Ron, File::create(&self.config.world_data_path(WorldEncoding::Ron))?)?;
+
+        // Le RON reste la source comparable dans git, le binaire est régénéré à côté pour être distribué
+        if self.config.world_encoding == WorldEncoding::Binary {
+            serialization::save_world(&self.world, &self.config.name, &self.config.author, WorldEncoding::Binary, File::create(&self.config.world_data_path(WorldEncoding::Binary))?)?;
+        }
+        self.world_version = serialization::WORLD_FORMAT_VERSION;
 
         self.config.initial_follow_entity = self.world.read_resource::<FollowEntity>().clone();
 
         self.config.save();
+
+        Ok(())
     }
 
     pub fn set_follow_camera(&self, entity: Option<Entity>) {
@@ -249,12 +562,39 @@ impl<'a, 'b> Level<'a, 'b> {
         }
     }
 
-    pub fn update(&mut self, _context: &mut Context, camera: &Camera, _dt: f32) {
+    fn step(&mut self) {
         self.dispatcher.dispatch(&self.world.res);
 
         self.chunk_sys.run_now(&self.world.res);
+    }
+
+    pub fn update(&mut self, context: &mut Context, camera: &Camera, dt: f32) {
+        self.world.write_resource::<DeltaTime>().0 = dt as f64;
+
+        self.step();
+
+        // Images modifiées depuis l'éditeur et textures des rafales de particules apparues pendant ce tick
+        if self.resources_dirty || self.has_unloaded_burst_textures() {
+            self.load_resources(context);
+            self.resources_dirty = false;
+        }
+
+        self.play_sounds(Some(camera));
+        self.audio.update(context, &mut self.resources_manager);
 
         self.world.write_resource::<ActiveChunksRect>().update_camera(camera);
         self.world.maintain();
     }
+
+    /// Met à jour le niveau sans caméra, tous les chunks sont considérés comme actifs.
+    pub fn update_headless(&mut self) {
+        let bounds = self.get_chunks_bounds();
+        self.world.write_resource::<ActiveChunksRect>().set_rect(bounds);
+
+        self.step();
+
+        self.play_sounds(None);
+
+        self.world.maintain();
+    }
 }
\ No newline at end of file
@@ -0,0 +1,252 @@
+use crate::{
+    ecs::{
+        chunk::ActiveChunkMarker,
+        level::DeltaTime,
+        rect::RectComponent,
+    },
+    utils::{
+        camera::Camera,
+        constants,
+        ggez::CtxExtension,
+        serde::ColorDef,
+    },
+};
+use ggez::{
+    Context,
+    GameResult,
+    graphics::{
+        self,
+        BlendMode,
+        Canvas,
+        Color,
+        DrawParam,
+        Image,
+        Point2,
+    },
+};
+use nalgebra::Vector2;
+use serde::{
+    Deserialize,
+    Serialize,
+};
+use specs::prelude::*;
+use specs_derive::Component;
+use std::collections::HashMap;
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+pub enum LightShape {
+    Point,
+    /// Cône orienté vers `direction`, d'ouverture `angle` (en degrés)
+    Cone { direction: f64, angle: f64 },
+}
+
+/// Source de lumière centrée sur le rectangle de l'entité.
+#[derive(Component, Serialize, Deserialize, Clone, Debug)]
+pub struct LightComponent {
+    pub shape: LightShape,
+    /// Rayon en pixels
+    pub radius: f64,
+    #[serde(with = "ColorDef")]
+    pub color: Color,
+    pub intensity: f64,
+    /// Amplitude du scintillement, de 0 (stable) à 1
+    pub flicker: f64,
+    #[serde(skip)]
+    time: f64,
+}
+
+impl Default for LightComponent {
+    fn default() -> Self {
+        LightComponent { shape: LightShape::Point, radius: 200., color: (255, 220, 180, 255).into(), intensity: 1., flicker: 0., time: 0. }
+    }
+}
+
+impl LightComponent {
+    /// Intensité actuelle, `seed` décale le scintillement des lumières entre elles.
+    pub fn current_intensity(&self, seed: u32) -> f64 {
+        let phase = seed as f64 * 1.7;
+        let noise = 0.5 + 0.25 * ((self.time * 7.3 + phase).sin() + (self.time * 13.1 + phase * 0.6).sin());
+
+        self.intensity * (1. - self.flicker.max(0.).min(1.) * noise)
+    }
+}
+
+/// Éclairage du niveau : une couche d'obscurité de couleur `ambient` que les lumières éclaircissent.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct LightingConfig {
+    pub enabled: bool,
+    #[serde(with = "ColorDef")]
+    pub ambient: Color,
+}
+
+impl Default for LightingConfig {
+    fn default() -> Self {
+        LightingConfig { enabled: false, ambient: (40, 40, 70, 255).into() }
+    }
+}
+
+pub struct LightSystem;
+
+impl<'a> System<'a> for LightSystem {
+    type SystemData = (Read<'a, DeltaTime>, WriteStorage<'a, LightComponent>);
+
+    fn run(&mut self, (dt, mut lights): Self::SystemData) {
+        for light in (&mut lights).join() {
+            light.time += dt.0;
+        }
+    }
+}
+
+/// Canevas de l'éclairage et textures des lumières, conservés entre les images.
+#[derive(Default)]
+pub struct LightRenderer {
+    canvas: Option<Canvas>,
+    /// Texture par ouverture de cône en degrés, `None` pour les lumières ponctuelles
+    textures: HashMap<Option<u32>, Image>,
+}
+
+impl LightRenderer {
+    /// Dégradé radial blanc, limité à un cône orienté vers la droite si `cone_angle` est donné.
+    fn create_texture(ctx: &mut Context, cone_angle: Option<u32>) -> GameResult<Image> {
+        let size = constants::LIGHT_TEXTURE_SIZE;
+        let center = size as f64 / 2.;
+
+        let mut rgba = Vec::with_capacity((size * size * 4) as usize);
+
+        for y in 0..size {
+            for x in 0..size {
+                let (dx, dy) = (x as f64 + 0.5 - center, y as f64 + 0.5 - center);
+
+                let falloff = (1. - (dx * dx + dy * dy).sqrt() / center).max(0.);
+                let mut value = falloff * falloff;
+
+                if let Some(angle) = cone_angle {
+                    let half_angle = (angle as f64).to_radians() / 2.;
+                    // Bord du cône adouci sur un dixième de l'ouverture
+                    let edge = (half_angle * 0.1).max(0.01);
+                    value *= ((half_angle - dy.atan2(dx).abs()) / edge).max(0.).min(1.);
+                }
+
+                let value = (value * 255.) as u8;
+                rgba.extend_from_slice(&[value, value, value, 255]);
+            }
+        }
+
+        Image::from_rgba8(ctx, size as u16, size as u16, &rgba)
+    }
+
+    fn get_texture(&mut self, ctx: &mut Context, shape: LightShape) -> GameResult<&Image> {
+        let key = match shape {
+            LightShape::Point => None,
+            LightShape::Cone { angle, .. } => Some(angle.max(1.).min(360.) as u32),
+        };
+
+        if !self.textures.contains_key(&key) {
+            let texture = Self::create_texture(ctx, key)?;
+            self.textures.insert(key, texture);
+        }
+
+        Ok(&self.textures[&key])
+    }
+
+    fn get_canvas(&mut self, ctx: &mut Context) -> GameResult<&Canvas> {
+        let screen_size = ctx.screen_size();
+
+        let outdated = self.canvas.as_ref().map_or(true, |canvas| {
+            let image = canvas.get_image();
+            image.width() != screen_size.x || image.height() != screen_size.y
+        });
+
+        if outdated {
+            self.canvas = Some(Canvas::with_window_size(ctx)?);
+        }
+
+        Ok(self.canvas.as_ref().unwrap())
+    }
+}
+
+/// Dessine les lumières dans un canevas rempli de la couleur ambiante, puis le compose avec l'écran selon `blend_mode`.
+pub struct LightingRenderSystem<'a> {
+    pub ctx: &'a mut Context,
+    pub camera: &'a Camera,
+    pub renderer: &'a mut LightRenderer,
+    pub ambient: Color,
+    pub blend_mode: BlendMode,
+}
+
+impl<'a> LightingRenderSystem<'a> {
+    fn draw_lightmap(&mut self, entities: &Entities, lights: &ReadStorage<LightComponent>, rects: &ReadStorage<RectComponent>, active_chunks: &ReadStorage<ActiveChunkMarker>) -> GameResult<()> {
+        let screen_size = self.ctx.screen_size();
+
+        // Le canevas et sa composition couvrent l'écran, seules les lumières suivent la transformation courante comme les entités
+        let world_transform = graphics::get_transform(self.ctx);
+        graphics::push_transform(self.ctx, Some(graphics::Matrix4::identity()));
+        graphics::apply_transformations(self.ctx)?;
+
+        let canvas = self.renderer.get_canvas(self.ctx)?;
+        graphics::set_canvas(self.ctx, Some(canvas));
+
+        graphics::set_blend_mode(self.ctx, BlendMode::Replace)?;
+        graphics::set_color(self.ctx, self.ambient)?;
+        graphics::rectangle(self.ctx, graphics::DrawMode::Fill, graphics::Rect::new(0., 0., screen_size.x as f32, screen_size.y as f32))?;
+
+        graphics::set_blend_mode(self.ctx, BlendMode::Add)?;
+
+        graphics::push_transform(self.ctx, Some(world_transform));
+        graphics::apply_transformations(self.ctx)?;
+
+        for (ent, light, rect, _) in (&**entities, lights, rects, active_chunks).join() {
+            let rect = rect.get_rect();
+
+            let center = self.camera.world_point_to_screen(nalgebra::Point2::new(rect.left() + rect.size.x as f64 / 2., rect.top() + rect.size.y as f64 / 2.));
+            let size = self.camera.world_size_to_screen(Vector2::new(light.radius * 2., light.radius * 2.));
+
+            let intensity = light.current_intensity(ent.id()) as f32;
+            let color = Color::new(light.color.r * intensity, light.color.g * intensity, light.color.b * intensity, 1.);
+
+            let rotation = match light.shape {
+                LightShape::Point => 0.,
+                LightShape::Cone { direction, .. } => direction.to_radians() as f32,
+            };
+
+            let texture_size = constants::LIGHT_TEXTURE_SIZE as f64;
+            let texture = self.renderer.get_texture(self.ctx, light.shape)?;
+
+            graphics::draw_ex(self.ctx, texture, DrawParam {
+                dest: Point2::new(center.x as f32, center.y as f32),
+                rotation,
+                offset: Point2::new(0.5, 0.5),
+                scale: Point2::new((size.x / texture_size) as f32, (size.y / texture_size) as f32),
+                color: Some(color),
+                ..Default::default()
+            })?;
+        }
+
+        graphics::pop_transform(self.ctx);
+        graphics::apply_transformations(self.ctx)?;
+
+        graphics::set_canvas(self.ctx, None);
+
+        graphics::set_blend_mode(self.ctx, self.blend_mode)?;
+        graphics::set_color(self.ctx, graphics::WHITE)?;
+        graphics::draw(self.ctx, self.renderer.canvas.as_ref().unwrap(), Point2::new(0., 0.), 0.)?;
+
+        graphics::pop_transform(self.ctx);
+        graphics::apply_transformations(self.ctx)?;
+
+        graphics::set_blend_mode(self.ctx, BlendMode::Alpha)
+    }
+}
+
+impl<'a> System<'a> for LightingRenderSystem<'a> {
+    type SystemData = (
+        Entities<'a>,
+        ReadStorage<'a, LightComponent>,
+        ReadStorage<'a, RectComponent>,
+        ReadStorage<'a, ActiveChunkMarker>,
+    );
+
+    fn run(&mut self, (entities, lights, rects, active_chunks): Self::SystemData) {
+        self.draw_lightmap(&entities, &lights, &rects, &active_chunks).unwrap();
+    }
+}
@@ -1,13 +1,45 @@
 use crate::{
-    ecs::render::SpriteComponent,
+    ecs::{
+        animation::AnimationComponent,
+        particles::{
+            self,
+            ParticleBursts,
+            ParticleEmitterComponent,
+        },
+        render::SpriteComponent,
+    },
     utils::resources_manager::ResourcesManager,
 };
 use ggez::Context;
 use specs::{
+    Entity,
     Join,
+    Read,
+    ReadStorage,
     System,
+    World,
     WriteStorage,
 };
+use std::path::PathBuf;
+
+/// Chemins des images utilisées par l'entité, l'éditeur les compare pour savoir quand recharger les ressources.
+pub fn entity_resource_paths(world: &World, ent: Entity) -> Vec<PathBuf> {
+    let mut paths = vec![];
+
+    if let Some(spr_image) = world.read_storage::<SpriteComponent>().get(ent).and_then(|spr| spr.image.as_ref()) {
+        paths.push(spr_image.get_path().clone());
+    }
+
+    if let Some(animation) = world.read_storage::<AnimationComponent>().get(ent) {
+        paths.extend(animation.source.images().into_iter().map(|spr_image| spr_image.get_path().clone()));
+    }
+
+    if let Some(texture) = world.read_storage::<ParticleEmitterComponent>().get(ent).and_then(|e| e.settings.texture.clone()) {
+        paths.push(texture);
+    }
+
+    paths
+}
 
 pub struct LoadingResourcesSystem<'a> {
     pub ctx: &'a mut Context,
@@ -15,17 +47,66 @@ pub struct LoadingResourcesSystem<'a> {
 }
 
 impl<'a> System<'a> for LoadingResourcesSystem<'a> {
-    type SystemData = WriteStorage<'a, SpriteComponent>;
+    type SystemData = (
+        WriteStorage<'a, SpriteComponent>,
+        WriteStorage<'a, AnimationComponent>,
+        ReadStorage<'a, ParticleEmitterComponent>,
+        Read<'a, ParticleBursts>,
+    );
 
-    fn run(&mut self, mut sprite: Self::SystemData) {
+    fn run(&mut self, (mut sprite, mut animations, emitters, bursts): Self::SystemData) {
         for spr in (&mut sprite).join() {
             if let Some(ref mut spr_image) = spr.image {
-                if !spr_image.is_loaded() {
-                    spr_image.load_after_deserialization(|path| {
-                        self.resources_manager.load_or_get_texture(self.ctx, path).unwrap().unwrap().clone()
-                    });
+                if spr_image.is_loaded() || self.resources_manager.has_failed(spr_image.get_path()) {
+                    continue;
+                }
+
+                match self.resources_manager.load_or_get_texture(self.ctx, spr_image.get_path()) {
+                    Ok(Some(image)) => {
+                        let image = image.clone();
+                        spr_image.load_after_deserialization(|_| image);
+                    }
+                    Ok(None) => {}
+                    Err(err) => eprintln!("Impossible de charger l'image {:?} du sprite : {}", spr_image.get_path(), err),
                 }
             }
         }
+
+        // Les images des animations sont modifiables depuis l'éditeur, un chemin invalide ne doit pas arrêter le jeu
+        for animation in (&mut animations).join() {
+            for spr_image in animation.source.images_mut() {
+                if spr_image.is_loaded() || self.resources_manager.has_failed(spr_image.get_path()) {
+                    continue;
+                }
+
+                match self.resources_manager.load_or_get_texture(self.ctx, spr_image.get_path()) {
+                    Ok(Some(image)) => {
+                        let image = image.clone();
+                        spr_image.load_after_deserialization(|_| image);
+                    }
+                    Ok(None) => {}
+                    Err(err) => eprintln!("Impossible de charger l'image {:?} de l'animation : {}", spr_image.get_path(), err),
+                }
+            }
+        }
+
+        if self.resources_manager.get_particle_texture().is_none() {
+            match particles::create_default_texture(self.ctx) {
+                Ok(image) => self.resources_manager.set_particle_texture(image),
+                Err(err) => eprintln!("Impossible de créer la texture des particules : {}", err),
+            }
+        }
+
+        let particle_textures: Vec<PathBuf> = emitters.join().filter_map(|e| e.settings.texture.clone()).chain(bursts.textures().cloned()).collect();
+
+        for path in particle_textures {
+            if self.resources_manager.get_texture(&path).is_some() || self.resources_manager.has_failed(&path) {
+                continue;
+            }
+
+            if let Err(err) = self.resources_manager.load_texture(self.ctx, &path) {
+                eprintln!("Impossible de charger la texture de particules {:?} : {}", path, err);
+            }
+        }
     }
-}
\ No newline at end of file
+}
@@ -1,21 +1,92 @@
 use crate::{
     ecs::{
-        actions::ActionComponent,
+        actions::{
+            ActionComponent,
+            ActionSystem,
+        },
+        ai::{
+            AiComponent,
+            AiSystem,
+        },
+        animation::{
+            AnimationComponent,
+            AnimationSystem,
+        },
+        audio::SoundQueue,
+        checkpoints::{
+            CheckpointComponent,
+            CheckpointSystem,
+            RespawnPoint,
+        },
+        collectibles::{
+            CollectibleComponent,
+            CollectibleSystem,
+            DoorComponent,
+            InventoryComponent,
+            Score,
+        },
         chunk::{
             ActiveChunkMarker,
             ActiveChunksRect,
             ChunkComponent,
+            ChunksGrid,
             ChunkSystem,
         },
-        inputs::InputComponent,
-        physics::PhysicsComponent,
+        goals::{
+            GoalsSystem,
+            LevelGoals,
+            LevelProgress,
+        },
+        health::{
+            DamageComponent,
+            HealthComponent,
+            HealthSystem,
+        },
+        inputs::{
+            InputComponent,
+            InputSystem,
+        },
+        lighting::{
+            LightComponent,
+            LightSystem,
+        },
+        particles::{
+            ParticleBursts,
+            ParticleEmitterComponent,
+            ParticleSystem,
+        },
+        path::{
+            PathComponent,
+            PathSystem,
+        },
+        physics::{
+            ContactsComponent,
+            PhysicsComponent,
+            PhysicsSystem,
+        },
         rect::RectComponent,
         render::SpriteComponent,
+        scripting::{
+            ScriptComponent,
+            Scripting,
+            ScriptSystem,
+        },
+        triggers::{
+            TriggerComponent,
+            TriggerSystem,
+        },
+        variables::VariablesComponent,
+    },
+    utils::{
+        constants,
+        input_manager::RefInputManager,
+        math::Rect,
     },
-    utils::math::Rect,
 };
+use nalgebra::Vector2;
 use specs::{
     Builder,
+    DispatcherBuilder,
     Entity,
     Join,
     LazyUpdate,
@@ -23,7 +94,10 @@ use specs::{
     System,
     World,
 };
-use crate::ecs::level::FollowEntity;
+use crate::ecs::level::{
+    DeltaTime,
+    FollowEntity,
+};
 
 pub mod level;
 pub mod actions;
@@ -35,6 +109,38 @@ pub mod render;
 pub mod rect;
 pub mod imgui_editor;
 pub mod loading;
+pub mod simulation;
+pub mod path;
+pub mod triggers;
+pub mod goals;
+pub mod checkpoints;
+pub mod health;
+pub mod collectibles;
+pub mod ai;
+pub mod variables;
+pub mod scripting;
+pub mod animation;
+pub mod lighting;
+pub mod particles;
+pub mod audio;
+
+/// Systèmes exécutés lorsque le niveau est joué (jeu, essai depuis l'éditeur, simulation).
+pub fn build_game_dispatcher<'a, 'b>(builder: DispatcherBuilder<'a, 'b>, input_manager: RefInputManager) -> DispatcherBuilder<'a, 'b> {
+    builder.with(InputSystem { input_manager: input_manager.clone() }, "input_manager", &[])
+        .with(AiSystem, "ai_sys", &[])
+        .with(ActionSystem, "action_system", &["input_manager", "ai_sys"])
+        .with(PathSystem, "path_sys", &[])
+        .with(PhysicsSystem { gravity: Vector2::new(0., constants::GRAVITY) }, "phys_sys", &["action_system", "path_sys"])
+        .with(TriggerSystem, "trigger_sys", &["phys_sys"])
+        .with(CheckpointSystem, "checkpoint_sys", &["phys_sys"])
+        .with(HealthSystem, "health_sys", &["phys_sys"])
+        .with(CollectibleSystem, "collectible_sys", &["phys_sys"])
+        .with(GoalsSystem, "goals_sys", &["phys_sys", "collectible_sys"])
+        .with(AnimationSystem, "anim_sys", &["phys_sys"])
+        .with(LightSystem, "light_sys", &[])
+        .with(ParticleSystem::default(), "particle_sys", &["action_system"])
+        .with_thread_local(ScriptSystem::new(input_manager))
+}
 
 pub fn create_default_world() -> (World, ChunkSystem) {
     let mut world = World::new();
@@ -44,6 +150,21 @@ pub fn create_default_world() -> (World, ChunkSystem) {
     world.register::<InputComponent>();
     world.register::<ActionComponent>();
     world.register::<PhysicsComponent>();
+    world.register::<ContactsComponent>();
+    world.register::<PathComponent>();
+    world.register::<TriggerComponent>();
+    world.register::<CheckpointComponent>();
+    world.register::<HealthComponent>();
+    world.register::<DamageComponent>();
+    world.register::<CollectibleComponent>();
+    world.register::<InventoryComponent>();
+    world.register::<DoorComponent>();
+    world.register::<AiComponent>();
+    world.register::<VariablesComponent>();
+    world.register::<ScriptComponent>();
+    world.register::<AnimationComponent>();
+    world.register::<LightComponent>();
+    world.register::<ParticleEmitterComponent>();
     world.register::<ChunkComponent>();
     world.register::<ActiveChunkMarker>();
     world.register::<U64Marker>();
@@ -51,8 +172,17 @@ pub fn create_default_world() -> (World, ChunkSystem) {
     world.add_resource(U64MarkerAllocator::new());
     world.add_resource(ActiveChunksRect::new(Rect::new(0., 0., 1000, 1000), 1.5));
     world.add_resource(FollowEntity::default());
+    world.add_resource(DeltaTime::default());
+    world.add_resource(LevelGoals::default());
+    world.add_resource(LevelProgress::default());
+    world.add_resource(RespawnPoint::default());
+    world.add_resource(Score::default());
+    world.add_resource(Scripting::default());
+    world.add_resource(ParticleBursts::default());
+    world.add_resource(SoundQueue::default());
+    world.add_resource(ChunksGrid::new((20, 5)));
 
-    let mut chunk_sys = ChunkSystem::new((20, 5), Rect::new(0., 0., 1280, 720));
+    let mut chunk_sys = ChunkSystem::new();
     chunk_sys.setup(&mut world.res);
     (world, chunk_sys)
 }
@@ -81,13 +211,32 @@ pub fn copy_world(copy_world: &World) -> (World, ChunkSystem) {
             add_copy_comp!(SpriteComponent);
             add_copy_comp!(PhysicsComponent);
             add_copy_comp!(InputComponent);
+            add_copy_comp!(PathComponent);
+            add_copy_comp!(TriggerComponent);
+            add_copy_comp!(CheckpointComponent);
+            add_copy_comp!(HealthComponent);
+            add_copy_comp!(DamageComponent);
+            add_copy_comp!(CollectibleComponent);
+            add_copy_comp!(InventoryComponent);
+            add_copy_comp!(DoorComponent);
+            add_copy_comp!(AiComponent);
+            add_copy_comp!(VariablesComponent);
+            add_copy_comp!(ScriptComponent);
+            add_copy_comp!(AnimationComponent);
+            add_copy_comp!(LightComponent);
+            add_copy_comp!(ParticleEmitterComponent);
+            add_copy_comp!(U64Marker);
 
             new_ent.build()
         };
     }
 
+    // Les identifiants restent ceux du monde copié, seule la correspondance avec les nouvelles entités est reconstruite
     *world.write_resource::<U64MarkerAllocator>() = copy_world.read_resource::<U64MarkerAllocator>().clone();
+    world.write_resource::<U64MarkerAllocator>().maintain(&world.entities(), &world.read_storage::<U64Marker>());
     world.write_resource::<FollowEntity>().0 =  copy_world.read_resource::<FollowEntity>().0;
+    *world.write_resource::<LevelGoals>() = copy_world.read_resource::<LevelGoals>().clone();
+    world.write_resource::<Scripting>().dir = copy_world.read_resource::<Scripting>().dir.clone();
 
     (world, chunk_sys)
 }
@@ -111,6 +260,20 @@ pub fn copy_entity(entity: Entity, world: &mut World) -> Entity {
         add_copy_comp!(SpriteComponent);
         add_copy_comp!(PhysicsComponent);
         add_copy_comp!(InputComponent);
+        add_copy_comp!(PathComponent);
+        add_copy_comp!(TriggerComponent);
+        add_copy_comp!(CheckpointComponent);
+        add_copy_comp!(HealthComponent);
+        add_copy_comp!(DamageComponent);
+        add_copy_comp!(CollectibleComponent);
+        add_copy_comp!(InventoryComponent);
+        add_copy_comp!(DoorComponent);
+        add_copy_comp!(AiComponent);
+        add_copy_comp!(VariablesComponent);
+        add_copy_comp!(ScriptComponent);
+        add_copy_comp!(AnimationComponent);
+        add_copy_comp!(LightComponent);
+        add_copy_comp!(ParticleEmitterComponent);
 
         let mut alloc = world.write_resource::<<U64Marker as Marker>::Allocator>();
         alloc.mark(copy_ent, &mut world.write_storage::<U64Marker>());
@@ -0,0 +1,349 @@
+use crate::{
+    ecs::{
+        chunk::ActiveChunkMarker,
+        level::DeltaTime,
+        rect::RectComponent,
+    },
+    utils::{
+        constants,
+        serde::ColorDef,
+    },
+};
+use ggez::{
+    Context,
+    GameResult,
+    graphics::{
+        Color,
+        Image,
+    },
+};
+use nalgebra::{
+    Point2,
+    Vector2,
+};
+use serde::{
+    Deserialize,
+    Serialize,
+};
+use specs::prelude::*;
+use specs_derive::Component;
+use std::path::PathBuf;
+
+/// Réglages communs aux émetteurs et aux particules émises par `Actions::SpawnParticles`.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct ParticleSettings {
+    /// Durée de vie d'une particule, en secondes
+    pub lifetime: f64,
+    /// Direction d'émission en degrés, -90 vers le haut
+    pub direction: f64,
+    /// Ouverture du cône d'émission, en degrés
+    pub spread: f64,
+    pub min_speed: f64,
+    pub max_speed: f64,
+    pub gravity: f64,
+    #[serde(with = "ColorDef")]
+    pub start_color: Color,
+    #[serde(with = "ColorDef")]
+    pub end_color: Color,
+    pub start_size: f64,
+    pub end_size: f64,
+    /// Texture chargée par le `ResourcesManager`, un disque flou est utilisé si aucune n'est donnée
+    pub texture: Option<PathBuf>,
+}
+
+impl Default for ParticleSettings {
+    fn default() -> Self {
+        ParticleSettings {
+            lifetime: 1.,
+            direction: -90.,
+            spread: 45.,
+            min_speed: 100.,
+            max_speed: 200.,
+            gravity: 300.,
+            start_color: (255, 255, 255, 255).into(),
+            end_color: (255, 255, 255, 0).into(),
+            start_size: 8.,
+            end_size: 2.,
+            texture: None,
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Particle {
+    pub pos: Point2<f64>,
+    velocity: Vector2<f64>,
+    age: f64,
+}
+
+impl Particle {
+    /// Couleur et taille de la particule, interpolées selon son âge.
+    pub fn appearance(&self, settings: &ParticleSettings) -> (Color, f64) {
+        let t = (self.age / settings.lifetime.max(0.001)).min(1.) as f32;
+        let (start, end) = (settings.start_color, settings.end_color);
+
+        let color = Color::new(start.r + (end.r - start.r) * t, start.g + (end.g - start.g) * t, start.b + (end.b - start.b) * t, start.a + (end.a - start.a) * t);
+
+        (color, settings.start_size + (settings.end_size - settings.start_size) * t as f64)
+    }
+}
+
+/// Générateur pseudo-aléatoire (xorshift), pour que les particules restent identiques d'une partie à l'autre.
+struct ParticleRng(u64);
+
+impl ParticleRng {
+    /// Nombre dans [0, 1[.
+    fn next_f64(&mut self) -> f64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+
+        (self.0 >> 11) as f64 / (1u64 << 53) as f64
+    }
+}
+
+fn spawn_particles(settings: &ParticleSettings, particles: &mut Vec<Particle>, pos: Point2<f64>, count: u32, rng: &mut ParticleRng) {
+    for _ in 0..count {
+        let angle = (settings.direction + (rng.next_f64() - 0.5) * settings.spread).to_radians();
+        let speed = settings.min_speed + (settings.max_speed - settings.min_speed) * rng.next_f64();
+
+        particles.push(Particle { pos, velocity: Vector2::new(angle.cos(), angle.sin()) * speed, age: 0. });
+    }
+}
+
+fn update_particles(settings: &ParticleSettings, particles: &mut Vec<Particle>, dt: f64) {
+    for particle in particles.iter_mut() {
+        particle.age += dt;
+        particle.velocity.y += settings.gravity * dt;
+        particle.pos += particle.velocity * dt;
+    }
+
+    particles.retain(|p| p.age < settings.lifetime);
+}
+
+/// Émet des particules en continu depuis le centre du rectangle de l'entité.
+#[derive(Component, Serialize, Deserialize, Clone, Debug)]
+pub struct ParticleEmitterComponent {
+    pub settings: ParticleSettings,
+    /// Particules émises par seconde
+    pub rate: f64,
+    pub enabled: bool,
+    #[serde(skip)]
+    particles: Vec<Particle>,
+    #[serde(skip)]
+    accumulator: f64,
+}
+
+impl Default for ParticleEmitterComponent {
+    fn default() -> Self {
+        ParticleEmitterComponent { settings: ParticleSettings::default(), rate: 20., enabled: true, particles: vec![], accumulator: 0. }
+    }
+}
+
+impl ParticleEmitterComponent {
+    pub fn get_particles(&self) -> &[Particle] { &self.particles }
+}
+
+/// Particules émises une seule fois, indépendantes de toute entité.
+pub struct ParticleGroup {
+    pub settings: ParticleSettings,
+    particles: Vec<Particle>,
+}
+
+impl ParticleGroup {
+    pub fn get_particles(&self) -> &[Particle] { &self.particles }
+}
+
+/// Rafales de particules demandées par les actions, simulées par le `ParticleSystem`.
+#[derive(Default)]
+pub struct ParticleBursts {
+    groups: Vec<ParticleGroup>,
+    requested: Vec<(ParticleSettings, Point2<f64>, u32)>,
+}
+
+impl ParticleBursts {
+    pub fn request(&mut self, settings: ParticleSettings, pos: Point2<f64>, count: u32) {
+        self.requested.push((settings, pos, count));
+    }
+
+    pub fn get_groups(&self) -> &[ParticleGroup] { &self.groups }
+
+    /// Textures des rafales en cours et à venir.
+    pub fn textures(&self) -> impl Iterator<Item = &PathBuf> {
+        self.groups.iter().map(|g| &g.settings).chain(self.requested.iter().map(|r| &r.0)).filter_map(|s| s.texture.as_ref())
+    }
+}
+
+/// Disque blanc aux bords adoucis, utilisé par les particules sans texture.
+pub fn create_default_texture(ctx: &mut Context) -> GameResult<Image> {
+    let size = constants::PARTICLE_TEXTURE_SIZE;
+    let center = size as f64 / 2.;
+
+    let mut rgba = Vec::with_capacity((size * size * 4) as usize);
+
+    for y in 0..size {
+        for x in 0..size {
+            let (dx, dy) = (x as f64 + 0.5 - center, y as f64 + 0.5 - center);
+            let alpha = (1. - (dx * dx + dy * dy).sqrt() / center).max(0.).min(1.);
+
+            rgba.extend_from_slice(&[255, 255, 255, (alpha.sqrt() * 255.) as u8]);
+        }
+    }
+
+    Image::from_rgba8(ctx, size as u16, size as u16, &rgba)
+}
+
+pub struct ParticleSystem {
+    rng: ParticleRng,
+}
+
+impl Default for ParticleSystem {
+    fn default() -> Self {
+        ParticleSystem { rng: ParticleRng(0x2545_F491_4F6C_DD1D) }
+    }
+}
+
+impl<'a> System<'a> for ParticleSystem {
+    type SystemData = (
+        Read<'a, DeltaTime>,
+        Write<'a, ParticleBursts>,
+        WriteStorage<'a, ParticleEmitterComponent>,
+        ReadStorage<'a, RectComponent>,
+        ReadStorage<'a, ActiveChunkMarker>,
+    );
+
+    fn run(&mut self, (dt, mut bursts, mut emitters, rects, active_chunk): Self::SystemData) {
+        let dt = dt.0;
+
+        for (emitter, rect, _) in (&mut emitters, &rects, &active_chunk).join() {
+            let rect = rect.get_rect();
+            let center = Point2::new(rect.left() + rect.size.x as f64 / 2., rect.top() + rect.size.y as f64 / 2.);
+
+            update_particles(&emitter.settings, &mut emitter.particles, dt);
+
+            if emitter.enabled {
+                emitter.accumulator += emitter.rate * dt;
+
+                let count = emitter.accumulator.floor();
+                emitter.accumulator -= count;
+
+                spawn_particles(&emitter.settings, &mut emitter.particles, center, count as u32, &mut self.rng);
+            }
+        }
+
+        let bursts = &mut *bursts;
+
+        for group in bursts.groups.iter_mut() {
+            update_particles(&group.settings, &mut group.particles, dt);
+        }
+
+        bursts.groups.retain(|g| !g.particles.is_empty());
+
+        for (settings, pos, count) in bursts.requested.drain(..) {
+            let mut particles = Vec::with_capacity(count as usize);
+            spawn_particles(&settings, &mut particles, pos, count, &mut self.rng);
+
+            bursts.groups.push(ParticleGroup { settings, particles });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        ecs::{
+            actions::{
+                ActionComponent,
+                Actions,
+            },
+            rect::RectComponent,
+            simulation::tests::load_test_level,
+        },
+        utils::{
+            constants,
+            math::Rect,
+        },
+    };
+    use specs::{
+        Builder,
+        Join,
+        saveload::{
+            MarkedBuilder,
+            U64Marker,
+        },
+    };
+    use super::{
+        ParticleBursts,
+        ParticleEmitterComponent,
+        ParticleSettings,
+    };
+
+    fn settings() -> ParticleSettings {
+        ParticleSettings { lifetime: 0.5, gravity: 0., ..ParticleSettings::default() }
+    }
+
+    #[test]
+    fn emitter_follows_rate_and_lifetime() {
+        let mut simulation = load_test_level("particles_emitter", |world| {
+            world.create_entity()
+                .with(RectComponent::new(Rect::new(100., 100., 50, 50)))
+                .with(ParticleEmitterComponent { settings: settings(), rate: 30., ..ParticleEmitterComponent::default() })
+                .marked::<U64Marker>()
+                .build();
+        });
+
+        simulation.run(constants::DESIRED_FPS as u64 * 2);
+
+        {
+            let world = simulation.get_level().get_world();
+
+            // 30 particules par seconde vivant une demi-seconde
+            let count = world.read_storage::<ParticleEmitterComponent>().join().next().unwrap().get_particles().len();
+            assert!((14..=16).contains(&count), "{} particules", count);
+
+            for emitter in (&mut world.write_storage::<ParticleEmitterComponent>()).join() {
+                emitter.enabled = false;
+            }
+        }
+
+        simulation.run(constants::DESIRED_FPS as u64);
+
+        let world = simulation.get_level().get_world();
+        assert!(world.read_storage::<ParticleEmitterComponent>().join().all(|emitter| emitter.get_particles().is_empty()));
+    }
+
+    #[test]
+    fn spawn_particles_action_creates_burst() {
+        let mut simulation = load_test_level("particles_burst", |world| {
+            world.create_entity()
+                .with(RectComponent::new(Rect::new(100., 100., 50, 50)))
+                .marked::<U64Marker>()
+                .build();
+        });
+
+        {
+            let world = simulation.get_level().get_world();
+
+            let ent = world.entities().join().next().unwrap();
+            ActionComponent::push_action(&mut world.write_storage::<ActionComponent>(), ent, Actions::SpawnParticles(settings(), 10));
+        }
+
+        // L'ActionSystem demande la rafale, le ParticleSystem la crée au même tick
+        simulation.step();
+
+        {
+            let bursts = simulation.get_level().get_world().read_resource::<ParticleBursts>();
+
+            assert!(bursts.requested.is_empty());
+            assert_eq!(bursts.get_groups().len(), 1);
+
+            let particles = bursts.get_groups()[0].get_particles();
+            assert_eq!(particles.len(), 10);
+            assert!(particles.iter().all(|p| (p.pos.x, p.pos.y) == (125., 125.)));
+        }
+
+        simulation.run(constants::DESIRED_FPS as u64);
+
+        assert!(simulation.get_level().get_world().read_resource::<ParticleBursts>().get_groups().is_empty());
+    }
+}
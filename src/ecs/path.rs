@@ -0,0 +1,136 @@
+use crate::ecs::{
+    chunk::ActiveChunkMarker,
+    level::DeltaTime,
+    rect::RectComponent,
+};
+use nalgebra::{
+    Point2,
+    Vector2,
+};
+use serde::{
+    Deserialize,
+    Serialize,
+};
+use specs::prelude::*;
+use specs_derive::Component;
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+pub enum PathMode {
+    Loop,
+    PingPong,
+    Once,
+}
+
+#[derive(Clone, Debug)]
+struct PathState {
+    target: usize,
+    forward: bool,
+    wait_ticks: u32,
+}
+
+impl Default for PathState {
+    fn default() -> Self {
+        PathState { target: 0, forward: true, wait_ticks: 0 }
+    }
+}
+
+/// Déplace l'entité de point en point, les points sont les positions successives du rectangle.
+#[derive(Component, Serialize, Deserialize, Clone, Debug)]
+pub struct PathComponent {
+    pub waypoints: Vec<Point2<f64>>,
+    pub mode: PathMode,
+    /// Vitesse en pixels par seconde
+    pub speed: f64,
+    /// Attente à chaque point, en ticks
+    pub wait_ticks: u32,
+    #[serde(skip)]
+    state: PathState,
+    #[serde(skip)]
+    last_move: Vector2<f64>,
+}
+
+impl Default for PathComponent {
+    fn default() -> Self {
+        PathComponent {
+            waypoints: vec![],
+            mode: PathMode::PingPong,
+            speed: 100.,
+            wait_ticks: 0,
+            state: PathState::default(),
+            last_move: Vector2::new(0., 0.),
+        }
+    }
+}
+
+impl PathComponent {
+    /// Déplacement effectué pendant le dernier tick.
+    pub fn get_last_move(&self) -> Vector2<f64> { self.last_move }
+
+    pub fn reset(&mut self) {
+        self.state = PathState::default();
+        self.last_move = Vector2::new(0., 0.);
+    }
+
+    fn next_target(&mut self) {
+        let len = self.waypoints.len();
+        let state = &mut self.state;
+
+        match self.mode {
+            PathMode::Loop => {
+                state.target = (state.target + 1) % len;
+            }
+            PathMode::PingPong => {
+                if state.forward && state.target + 1 >= len {
+                    state.forward = false;
+                } else if !state.forward && state.target == 0 {
+                    state.forward = true;
+                }
+
+                state.target = if state.forward { (state.target + 1).min(len - 1) } else { state.target.saturating_sub(1) };
+            }
+            PathMode::Once => {
+                state.target = (state.target + 1).min(len - 1);
+            }
+        }
+    }
+}
+
+pub struct PathSystem;
+
+impl<'a> System<'a> for PathSystem {
+    type SystemData = (
+        Read<'a, DeltaTime>,
+        WriteStorage<'a, PathComponent>,
+        WriteStorage<'a, RectComponent>,
+        ReadStorage<'a, ActiveChunkMarker>,
+    );
+
+    fn run(&mut self, (dt, mut paths, mut rects, active_chunk): Self::SystemData) {
+        for (path, rect, _) in (&mut paths, &mut rects, &active_chunk).join() {
+            path.last_move = Vector2::new(0., 0.);
+
+            if path.waypoints.is_empty() {
+                continue;
+            }
+
+            if path.state.wait_ticks > 0 {
+                path.state.wait_ticks -= 1;
+                continue;
+            }
+
+            let to_target = path.waypoints[path.state.target.min(path.waypoints.len() - 1)] - rect.pos();
+            let distance = to_target.norm();
+            let step = path.speed * dt.0;
+
+            let mv = if distance <= step { to_target } else { to_target / distance * step };
+
+            rect.move_by(mv);
+            path.last_move = mv;
+
+            if distance <= step {
+                path.state.wait_ticks = path.wait_ticks;
+                path.next_target();
+            }
+        }
+    }
+}
@@ -1,7 +1,13 @@
 use crate::{
     ecs::{
-        chunk::ActiveChunkMarker,
+        chunk::{
+            ActiveChunkMarker,
+            ChunksGrid,
+        },
+        level::DeltaTime,
+        path::PathComponent,
         rect::RectComponent,
+        triggers::TriggerComponent,
     },
     utils::{
         constants,
@@ -10,7 +16,6 @@ use crate::{
 };
 use nalgebra::{
     self,
-    Point2,
     Vector2,
 };
 use num;
@@ -23,93 +28,418 @@ use specs_derive::Component;
 use std::collections::VecDeque;
 
 
-#[derive(Debug, Clone)]
-pub struct JumpData {
-    target_height: i32
-}
-
-impl JumpData {
-    fn new(target_height: i32) -> Self {
-        JumpData {
-            target_height
-        }
-    }
-}
-
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum BodyType {
     Static,
     Dynamic {
         apply_gravity: bool,
-        #[serde(skip)]
-        jump_data: Option<JumpData>,
     },
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum NextPhysicsStep {
+    /// Déplacement direct, indépendant de la vitesse
     Move(Vector2<f64>),
-    Jump(u32),
+    /// Saut jusqu'à la hauteur donnée, tamponné si le corps n'est pas encore au sol.
+    /// Un saut variable est coupé dès qu'il n'est plus demandé à chaque tick.
+    Jump(u32, bool),
+    /// Changement instantané de la vitesse
+    Impulse(Vector2<f64>),
+    /// Force appliquée pendant ce tick, multipliée par l'accélération du corps
+    Force(Vector2<f64>),
+    /// Traverse les plateformes traversables sous le corps
+    DropThrough,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum SlopeDirection {
+    UpRight,
+    UpLeft,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum SlopeAngle {
+    /// 45°
+    Steep,
+    /// 22.5°
+    Gentle,
+}
+
+impl SlopeAngle {
+    pub fn tangent(&self) -> f64 {
+        match self {
+            SlopeAngle::Steep => 1.,
+            SlopeAngle::Gentle => 0.414_213_562,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum CollisionShape {
+    Box,
+    /// Traversable par le dessous, et par le dessus avec `NextPhysicsStep::DropThrough`
+    OneWay,
+    /// Pente partant du coin inférieur du rectangle
+    Slope(SlopeDirection, SlopeAngle),
+}
+
+impl Default for CollisionShape {
+    fn default() -> Self {
+        CollisionShape::Box
+    }
+}
+
+impl CollisionShape {
+    /// Ordonnée de la surface de la forme à l'abscisse donnée.
+    pub fn surface_y(&self, rect: Rect, x: f64) -> f64 {
+        let x = num::clamp(x, rect.left(), rect.right());
+
+        match self {
+            CollisionShape::Box | CollisionShape::OneWay => rect.top(),
+            CollisionShape::Slope(SlopeDirection::UpRight, angle) => (rect.bottom() - (x - rect.left()) * angle.tangent()).max(rect.top()),
+            CollisionShape::Slope(SlopeDirection::UpLeft, angle) => (rect.bottom() - (rect.right() - x) * angle.tangent()).max(rect.top()),
+        }
+    }
+
+    /// Vrai si la forme bloque un corps se déplaçant horizontalement dans la direction donnée.
+    /// Les plateformes traversables ne bloquent jamais, les pentes seulement par leur côté le plus haut.
+    pub fn blocks_horizontally(&self, direction: f64) -> bool {
+        match self {
+            CollisionShape::Box => true,
+            CollisionShape::OneWay => false,
+            CollisionShape::Slope(SlopeDirection::UpRight, _) => direction < 0.,
+            CollisionShape::Slope(SlopeDirection::UpLeft, _) => direction > 0.,
+        }
+    }
+}
+
+/// Paramètres de mouvement des corps dynamiques, en pixels par seconde.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MovementProperties {
+    pub max_speed: Vector2<f64>,
+    pub acceleration: f64,
+    pub ground_friction: f64,
+    pub air_friction: f64,
+    pub gravity_scale: f64,
+}
+
+impl Default for MovementProperties {
+    fn default() -> Self {
+        MovementProperties {
+            max_speed: Vector2::new(600., 1500.),
+            acceleration: 3000.,
+            ground_friction: 3000.,
+            air_friction: 500.,
+            gravity_scale: 1.,
+        }
+    }
+}
+
+/// Paramètres du saut, en ticks.
+/// La coupure ne s'applique qu'aux sauts variables, lancés par une touche maintenue :
+/// les sauts ponctuels (déclencheurs, scripts, IA) atteignent toujours la hauteur demandée.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JumpProperties {
+    pub coyote_ticks: u32,
+    pub buffer_ticks: u32,
+    pub cut_factor: f64,
+}
+
+impl Default for JumpProperties {
+    fn default() -> Self {
+        JumpProperties {
+            coyote_ticks: 10,
+            buffer_ticks: 15,
+            cut_factor: 0.5,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct JumpState {
+    ticks_since_ground: u32,
+    /// Hauteur, saut variable et ticks restants
+    buffered_jump: Option<(u32, bool, u32)>,
+    held: bool,
+    jumping: bool,
+    variable: bool,
+}
+
+impl JumpState {
+    pub fn reset(&mut self) {
+        *self = JumpState::default();
+    }
 }
 
 #[derive(Component, Serialize, Deserialize, Clone)]
 pub struct PhysicsComponent {
     pub body_type: BodyType,
+    #[serde(default)]
+    pub shape: CollisionShape,
+    #[serde(default)]
+    pub movement: MovementProperties,
+    #[serde(default)]
+    pub jump: JumpProperties,
+    #[serde(skip)]
+    pub velocity: Vector2<f64>,
+    #[serde(skip)]
+    pub jump_state: JumpState,
+    #[serde(skip)]
+    drop_through_ticks: u32,
     next_physics_step: VecDeque<NextPhysicsStep>,
 }
 
 impl PhysicsComponent {
     pub fn new(body_type: BodyType) -> Self {
-        PhysicsComponent { body_type, next_physics_step: VecDeque::new() }
+        PhysicsComponent {
+            body_type,
+            shape: CollisionShape::default(),
+            movement: MovementProperties::default(),
+            jump: JumpProperties::default(),
+            velocity: Vector2::new(0., 0.),
+            jump_state: JumpState::default(),
+            drop_through_ticks: 0,
+            next_physics_step: VecDeque::new(),
+        }
     }
 
     pub fn add_step(&mut self, step: NextPhysicsStep) {
         self.next_physics_step.push_back(step);
     }
+
+    /// Arrête le corps : vitesse, saut en cours et étapes en attente sont remis à zéro.
+    pub fn reset_motion(&mut self) {
+        self.velocity = Vector2::new(0., 0.);
+        self.jump_state.reset();
+        self.drop_through_ticks = 0;
+        self.next_physics_step.clear();
+    }
 }
 
 impl Default for PhysicsComponent {
     fn default() -> Self {
-        PhysicsComponent { body_type: BodyType::Static, next_physics_step: VecDeque::new() }
+        PhysicsComponent::new(BodyType::Static)
+    }
+}
+
+/// Côté du corps qui touche une autre entité, `Bottom` signifie que le corps repose sur l'entité.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ContactSide {
+    Left,
+    Right,
+    Top,
+    Bottom,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Contact {
+    pub side: ContactSide,
+    pub entity: Entity,
+}
+
+/// Contacts du dernier tick physique, recalculés à chaque passage du `PhysicsSystem`.
+#[derive(Component, Default, Debug, Clone)]
+pub struct ContactsComponent {
+    pub contacts: Vec<Contact>
+}
+
+impl ContactsComponent {
+    pub fn has_side(&self, side: ContactSide) -> bool {
+        self.contacts.iter().any(|c| c.side == side)
+    }
+
+    pub fn is_grounded(&self) -> bool { self.has_side(ContactSide::Bottom) }
+
+    pub fn is_touching_wall(&self) -> bool { self.has_side(ContactSide::Left) || self.has_side(ContactSide::Right) }
+
+    pub fn is_hitting_ceiling(&self) -> bool { self.has_side(ContactSide::Top) }
+
+    fn add(&mut self, contact: Contact) {
+        if !self.contacts.contains(&contact) {
+            self.contacts.push(contact);
+        }
     }
 }
 
+/// Vrai si le rectangle chevauche un corps physique, autre que `ignore`, dont la forme satisfait `filter`.
+/// Les zones de déclenchement ne sont pas des corps solides et sont ignorées, comme dans le `PhysicsSystem`.
+pub fn is_body_in_rect<F: Fn(CollisionShape) -> bool>(rect: Rect, ignore: Entity, chunks_grid: &ChunksGrid, rects: &ReadStorage<RectComponent>, physics: &ReadStorage<PhysicsComponent>, triggers: &ReadStorage<TriggerComponent>, filter: F) -> bool {
+    chunks_grid.get_entities_in_rect(rect).into_iter()
+        .filter(|e| *e != ignore && !triggers.contains(*e))
+        .filter_map(|e| match (physics.get(e), rects.get(e)) {
+            (Some(p), Some(r)) => Some((p.shape, r.get_rect())),
+            _ => None
+        })
+        .any(|(shape, r)| filter(shape) && r.overlaps(rect))
+}
+
+struct Collider {
+    entity: Entity,
+    rect: Rect,
+    shape: CollisionShape,
+}
+
+#[derive(Clone, Copy)]
+enum Axis {
+    Horizontal,
+    Vertical,
+}
+
 pub struct PhysicsSystem {
     pub gravity: Vector2<f64>
 }
 
 impl PhysicsSystem {
-    // Check if moving the rect in axis x and/or y is possible (AABB)
-    fn check_move_aabb(mv_rect: &mut Rect, other_rects: &Vec<Rect>, mv_x: f64, mv_y: f64) -> (bool, bool) {
-        let mut can_move_x = true;
-        let mut can_move_y = true;
-
-        for other_rect in other_rects.iter() {
-            if Rect::from(Point2::new(mv_rect.pos.x + mv_x as f64, mv_rect.pos.y), mv_rect.size).overlaps(*other_rect) {
-                can_move_x = false;
+    /// Swept AABB sur un axe : retourne le déplacement possible jusqu'au premier contact et l'entité touchée.
+    fn sweep(mv_rect: Rect, colliders: &[Collider], mv: f64, axis: Axis, drop_through: bool) -> (f64, Option<Entity>) {
+        let mut allowed = mv;
+        let mut hit = None;
+
+        if mv == 0. {
+            return (allowed, hit);
+        }
+
+        for collider in colliders.iter() {
+            let other_rect = collider.rect;
+
+            let (overlaps_other_axis, distance) = match (axis, collider.shape) {
+                (Axis::Horizontal, shape) if !shape.blocks_horizontally(mv) => continue,
+                // Les plateformes traversables ne bloquent que par le dessus
+                (Axis::Vertical, CollisionShape::OneWay) if mv < 0. || drop_through => continue,
+                (Axis::Vertical, CollisionShape::Slope(direction, _)) if mv > 0. => (
+                    mv_rect.left() < other_rect.right() && mv_rect.right() > other_rect.left(),
+                    collider.shape.surface_y(other_rect, Self::slope_foot_x(mv_rect, direction)) - mv_rect.bottom()
+                ),
+                (Axis::Horizontal, _) => (
+                    mv_rect.top() < other_rect.bottom() && mv_rect.bottom() > other_rect.top(),
+                    if mv > 0. { other_rect.left() - mv_rect.right() } else { mv_rect.left() - other_rect.right() }
+                ),
+                (Axis::Vertical, _) => (
+                    mv_rect.left() < other_rect.right() && mv_rect.right() > other_rect.left(),
+                    if mv > 0. { other_rect.top() - mv_rect.bottom() } else { mv_rect.top() - other_rect.bottom() }
+                ),
+            };
+
+            // Les rectangles déjà superposés ou situés derrière le corps sont ignorés
+            if overlaps_other_axis && distance >= -constants::PHYSICS_EPSILON && distance <= nalgebra::abs(&allowed) {
+                allowed = distance.max(0.) * num::signum(mv);
+                hit = Some(collider.entity);
+            }
+        }
+
+        (allowed, hit)
+    }
+
+    /// Point du corps le plus haut sur la pente.
+    fn slope_foot_x(mv_rect: Rect, direction: SlopeDirection) -> f64 {
+        match direction {
+            SlopeDirection::UpRight => mv_rect.right(),
+            SlopeDirection::UpLeft => mv_rect.left(),
+        }
+    }
+
+    /// Remonte le corps sur les pentes qu'il gravit après un déplacement horizontal.
+    fn climb_slopes(mv_rect: &mut Rect, colliders: &[Collider], mv_x: f64, contacts: &mut ContactsComponent) {
+        for collider in colliders.iter() {
+            if let CollisionShape::Slope(direction, angle) = collider.shape {
+                if mv_rect.left() < collider.rect.right() && mv_rect.right() > collider.rect.left() {
+                    let penetration = mv_rect.bottom() - collider.shape.surface_y(collider.rect, Self::slope_foot_x(*mv_rect, direction));
+
+                    if penetration > 0. && penetration <= nalgebra::abs(&mv_x) * angle.tangent() + constants::PHYSICS_EPSILON {
+                        mv_rect.move_by(Vector2::new(0., -penetration));
+                        contacts.add(Contact { side: ContactSide::Bottom, entity: collider.entity });
+                    }
+                }
             }
+        }
+    }
+
+    fn move_body(mv_rect: &mut Rect, colliders: &[Collider], mv: Vector2<f64>, drop_through: bool, contacts: &mut ContactsComponent) {
+        let (mv_x, hit_x) = Self::sweep(*mv_rect, colliders, mv.x, Axis::Horizontal, drop_through);
+        mv_rect.move_by(Vector2::new(mv_x, 0.));
+
+        if let Some(entity) = hit_x {
+            contacts.add(Contact { side: if mv.x > 0. { ContactSide::Right } else { ContactSide::Left }, entity });
+        }
+
+        if mv_x != 0. {
+            Self::climb_slopes(mv_rect, colliders, mv_x, contacts);
+        }
+
+        let (mv_y, hit_y) = Self::sweep(*mv_rect, colliders, mv.y, Axis::Vertical, drop_through);
+        mv_rect.move_by(Vector2::new(0., mv_y));
+
+        if let Some(entity) = hit_y {
+            contacts.add(Contact { side: if mv.y > 0. { ContactSide::Bottom } else { ContactSide::Top }, entity });
+        }
+    }
+
+    /// Rectangle couvrant tous les déplacements possibles du corps pendant ce tick.
+    fn swept_bounds(mv_rect: Rect, steps: &VecDeque<NextPhysicsStep>, max_velocity_move: Vector2<f64>) -> Rect {
+        let mut reach = Vector2::new(constants::PHYSICS_EPSILON + max_velocity_move.x, constants::PHYSICS_EPSILON + max_velocity_move.y);
 
-            if Rect::from(Point2::new(mv_rect.pos.x as f64, mv_rect.pos.y + mv_y as f64), mv_rect.size).overlaps(*other_rect) {
-                can_move_y = false;
+        for step in steps.iter() {
+            if let NextPhysicsStep::Move(mv) = step {
+                reach.x += nalgebra::abs(&mv.x);
+                reach.y += nalgebra::abs(&mv.y);
             }
         }
 
-        (can_move_x, can_move_y)
+        Rect::new(mv_rect.left() - reach.x, mv_rect.top() - reach.y, mv_rect.size.x + (reach.x * 2.).ceil() as u32, mv_rect.size.y + (reach.y * 2.).ceil() as u32)
     }
 
-    fn move_rec(mv_rect: &mut Rect, other_rects: &Vec<Rect>, mv_x: f64, mv_y: f64) {
-        let (can_move_x, can_move_y) = Self::check_move_aabb(mv_rect, other_rects, mv_x, mv_y);
+    fn is_on_ground(mv_rect: Rect, colliders: &[Collider], drop_through: bool) -> bool {
+        Self::sweep(mv_rect, colliders, constants::PHYSICS_EPSILON, Axis::Vertical, drop_through).1.is_some()
+    }
+
+    /// Saut tamponné, temps coyote et coupure des sauts variables lorsque la touche est relâchée.
+    fn update_jump(jump: &JumpProperties, state: &mut JumpState, velocity: &mut Vector2<f64>, gravity: f64, on_ground: bool, jump_requested: Option<(u32, bool)>) {
+        if on_ground {
+            state.ticks_since_ground = 0;
+
+            if velocity.y >= 0. {
+                state.jumping = false;
+            }
+        } else {
+            state.ticks_since_ground = state.ticks_since_ground.saturating_add(1);
+        }
+
+        if let Some((height, variable)) = jump_requested {
+            if !state.held {
+                state.buffered_jump = Some((height, variable, jump.buffer_ticks));
+            }
+        }
+
+        state.held = jump_requested.is_some();
 
-        if can_move_x {
-            mv_rect.move_by(Vector2::new(mv_x, 0.));
-        } else if nalgebra::abs(&mv_x) > constants::PHYSICS_EPSILON {
-            Self::move_rec(mv_rect, other_rects, mv_x - (constants::PHYSICS_EPSILON * num::signum(mv_x)), 0.);
+        if let Some((height, variable, ticks_left)) = state.buffered_jump {
+            if !state.jumping && state.ticks_since_ground <= jump.coyote_ticks {
+                velocity.y = -(2. * gravity * height as f64).sqrt();
+                state.jumping = true;
+                state.variable = variable;
+                state.buffered_jump = None;
+                state.ticks_since_ground = jump.coyote_ticks.saturating_add(1);
+            } else if ticks_left == 0 {
+                state.buffered_jump = None;
+            } else {
+                state.buffered_jump = Some((height, variable, ticks_left - 1));
+            }
         }
 
-        if can_move_y {
-            mv_rect.move_by(Vector2::new(0., mv_y));
-        } else if nalgebra::abs(&mv_y) > constants::PHYSICS_EPSILON {
-            Self::move_rec(mv_rect, other_rects, 0., mv_y - (constants::PHYSICS_EPSILON * num::signum(mv_y)));
+        if state.jumping && velocity.y >= 0. {
+            state.jumping = false;
+        } else if state.jumping && state.variable && !state.held {
+            velocity.y *= jump.cut_factor;
+            state.jumping = false;
+        }
+    }
+
+    /// Rapproche la valeur de 0 sans la dépasser.
+    fn apply_friction(value: f64, friction: f64) -> f64 {
+        if nalgebra::abs(&value) <= friction {
+            0.
+        } else {
+            value - friction * num::signum(value)
         }
     }
 }
@@ -117,60 +447,118 @@ impl PhysicsSystem {
 impl<'a> System<'a> for PhysicsSystem {
     type SystemData = (
         Entities<'a>,
+        Read<'a, DeltaTime>,
         WriteStorage<'a, PhysicsComponent>,
         WriteStorage<'a, RectComponent>,
-        ReadStorage<'a, ActiveChunkMarker>
+        WriteStorage<'a, ContactsComponent>,
+        ReadStorage<'a, PathComponent>,
+        ReadStorage<'a, TriggerComponent>,
+        ReadStorage<'a, ActiveChunkMarker>,
+        ReadExpect<'a, ChunksGrid>,
     );
 
-    fn run(&mut self, (entities, mut physics, mut rects, active_chunk): Self::SystemData) {
-        for (ent, physics_comp, _) in (&*entities, &mut physics, &active_chunk).join() {
-            if let BodyType::Dynamic { ref apply_gravity, ref mut jump_data } = physics_comp.body_type {
-                let next_physics_step = &mut physics_comp.next_physics_step;
+    fn run(&mut self, (entities, dt, mut physics, mut rects, mut contacts, paths, triggers, active_chunk, chunks_grid): Self::SystemData) {
+        let dt = dt.0;
 
-                let mut stop_jump = if let Some(jump_data) = jump_data {
-                    next_physics_step.push_back(NextPhysicsStep::Move(Vector2::new(0., -self.gravity.y)));
+        let bodies: Vec<(Entity, bool)> = (&*entities, &physics, &active_chunk).join().filter_map(|(ent, physics_comp, _)| match physics_comp.body_type {
+            BodyType::Dynamic { apply_gravity } => Some((ent, apply_gravity)),
+            BodyType::Static => None
+        }).collect();
 
-                    if jump_data.target_height >= rects.get_mut(ent).unwrap().get_rect().pos.y as i32 - self.gravity.y as i32 {
-                        true
-                    } else {
-                        false
-                    }
-                } else {
-                    if *apply_gravity {
-                        next_physics_step.push_back(NextPhysicsStep::Move(self.gravity));
-                    }
+        for (ent, apply_gravity) in bodies {
+            // Les corps posés sur une plateforme mobile sont déplacés avec elle
+            let carried_by = contacts.get(ent).and_then(|c| {
+                c.contacts.iter().filter(|c| c.side == ContactSide::Bottom).filter_map(|c| paths.get(c.entity)).map(|p| p.get_last_move()).next()
+            });
+
+            if let Some(platform_move) = carried_by {
+                physics.get_mut(ent).unwrap().next_physics_step.push_front(NextPhysicsStep::Move(platform_move));
+            }
 
-                    false
-                };
+            let swept_bounds = {
+                let physics_comp = physics.get(ent).unwrap();
+                Self::swept_bounds(rects.get(ent).unwrap().get_rect(), &physics_comp.next_physics_step, physics_comp.movement.max_speed * dt)
+            };
 
-                if !next_physics_step.is_empty() {
-                    let other_rects: Vec<Rect> = (&*entities, &mut rects, &active_chunk).join().filter(|(e, _r, _)| { *e != ent }).map(|(_e, r, _)| { r.get_rect().clone() }).collect();
-                    let this_rect = rects.get_mut(ent).unwrap().get_rect_mut();
+            // Les formes sont lues avant d'emprunter le corps mutablement.
+            // Les zones de déclenchement sont détectées par chevauchement dans le `TriggerSystem` et ne bloquent pas les corps.
+            let colliders: Vec<Collider> = chunks_grid.get_entities_in_rect(swept_bounds).into_iter()
+                .filter(|e| *e != ent && entities.is_alive(*e) && active_chunk.contains(*e) && !triggers.contains(*e))
+                .filter_map(|e| match (rects.get(e), physics.get(e)) {
+                    (Some(r), Some(p)) => Some(Collider { entity: e, rect: r.get_rect(), shape: p.shape }),
+                    _ => None
+                })
+                .filter(|c| c.rect.overlaps(swept_bounds))
+                .collect();
 
-                    if !stop_jump {
-                        stop_jump = !Self::check_move_aabb(this_rect, &other_rects, 0., -constants::PHYSICS_EPSILON).1;
-                    }
+            let PhysicsComponent { ref movement, ref jump, ref mut velocity, ref mut jump_state, ref mut drop_through_ticks, ref mut next_physics_step, .. } = *physics.get_mut(ent).unwrap();
+
+            let gravity = self.gravity * movement.gravity_scale;
 
-                    while let Some(step) = next_physics_step.pop_front() {
-                        match step {
-                            NextPhysicsStep::Move(mv) => {
-                                Self::move_rec(this_rect, &other_rects, mv.x, mv.y);
-                            }
-                            NextPhysicsStep::Jump(height) => {
-                                if let None = jump_data {
-                                    if !Self::check_move_aabb(this_rect, &other_rects, 0., constants::PHYSICS_EPSILON).1 {
-                                        *jump_data = Some(JumpData::new(this_rect.pos.y as i32 - height as i32));
-                                    }
-                                }
-                            }
-                        }
+            let this_rect = rects.get_mut(ent).unwrap().get_rect_mut();
+
+            if next_physics_step.iter().any(|step| if let NextPhysicsStep::DropThrough = step { true } else { false }) {
+                *drop_through_ticks = constants::PHYSICS_DROP_THROUGH_TICKS;
+            }
+
+            let drop_through = *drop_through_ticks > 0;
+            *drop_through_ticks = drop_through_ticks.saturating_sub(1);
+
+            let mut ent_contacts = ContactsComponent::default();
+            let mut moves = vec![];
+            let mut driven_x = false;
+            let mut jump_requested = None;
+
+            while let Some(step) = next_physics_step.pop_front() {
+                match step {
+                    NextPhysicsStep::Move(mv) => {
+                        moves.push(mv);
+                    }
+                    NextPhysicsStep::Jump(height, variable) => {
+                        jump_requested = Some((height, variable));
                     }
+                    NextPhysicsStep::Impulse(impulse) => {
+                        *velocity += impulse;
+                    }
+                    NextPhysicsStep::Force(force) => {
+                        *velocity += force * movement.acceleration * dt;
+                        driven_x |= force.x != 0.;
+                    }
+                    NextPhysicsStep::DropThrough => {}
                 }
+            }
 
-                if stop_jump {
-                    *jump_data = None;
-                }
+            for mv in moves {
+                Self::move_body(this_rect, &colliders, mv, drop_through, &mut ent_contacts);
+            }
+
+            let on_ground = Self::is_on_ground(*this_rect, &colliders, drop_through);
+
+            Self::update_jump(jump, jump_state, velocity, gravity.y, on_ground, jump_requested);
+
+            if apply_gravity {
+                *velocity += gravity * dt;
+            }
+
+            if !driven_x {
+                let friction = if on_ground { movement.ground_friction } else { movement.air_friction };
+                velocity.x = Self::apply_friction(velocity.x, friction * dt);
+            }
+
+            velocity.x = num::clamp(velocity.x, -movement.max_speed.x, movement.max_speed.x);
+            velocity.y = num::clamp(velocity.y, -movement.max_speed.y, movement.max_speed.y);
+
+            Self::move_body(this_rect, &colliders, *velocity * dt, drop_through, &mut ent_contacts);
+
+            if (velocity.x > 0. && ent_contacts.has_side(ContactSide::Right)) || (velocity.x < 0. && ent_contacts.has_side(ContactSide::Left)) {
+                velocity.x = 0.;
             }
+
+            if (velocity.y > 0. && ent_contacts.is_grounded()) || (velocity.y < 0. && ent_contacts.is_hitting_ceiling()) {
+                velocity.y = 0.;
+            }
+
+            contacts.insert(ent, ent_contacts).unwrap();
         }
     }
-}
\ No newline at end of file
+}
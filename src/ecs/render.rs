@@ -1,10 +1,18 @@
 use crate::{
     ecs::{
+        animation::AnimationComponent,
         chunk::ActiveChunkMarker,
+        particles::{
+            Particle,
+            ParticleBursts,
+            ParticleEmitterComponent,
+            ParticleSettings,
+        },
         rect::RectComponent,
     },
     utils::{
         camera::Camera,
+        ggez::CtxExtension,
         math::Rect,
         resources_manager::ResourcesManager,
     },
@@ -30,7 +38,10 @@ use specs::prelude::*;
 use specs_derive::Component;
 use std::{
     num::NonZeroU32,
-    path::PathBuf,
+    path::{
+        Path,
+        PathBuf,
+    },
 };
 
 
@@ -72,21 +83,67 @@ impl SpriteImage {
         self.image = Some(f(&self.path));
     }
 
+    pub fn get_path(&self) -> &PathBuf { &self.path }
+
+    pub fn get_image(&self) -> Option<&Image> { self.image.as_ref() }
+
     pub fn update(&mut self, path: PathBuf, ctx: &mut Context, resources_manager: &mut ResourcesManager) {
         self.image = resources_manager.load_or_get_texture(ctx, &path).unwrap().cloned();
         self.path = path;
     }
 }
 
+/// Calques dessinés dans l'ordre : arrière-plan, jeu puis premier plan.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum RenderLayer {
+    Background,
+    Gameplay,
+    Foreground,
+}
+
+impl Default for RenderLayer {
+    fn default() -> Self { RenderLayer::Gameplay }
+}
+
+/// Facteurs de défilement par rapport à la caméra : 0 reste fixe à l'écran, 1 suit le monde.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct ParallaxConfig {
+    pub background: f64,
+    pub foreground: f64,
+    /// Facteur de la texture d'arrière-plan du niveau
+    pub texture: f64,
+}
+
+impl Default for ParallaxConfig {
+    fn default() -> Self {
+        ParallaxConfig { background: 0.5, foreground: 1.2, texture: 0.2 }
+    }
+}
+
+impl ParallaxConfig {
+    pub fn get_factor(&self, layer: RenderLayer) -> f64 {
+        match layer {
+            RenderLayer::Background => self.background,
+            RenderLayer::Gameplay => 1.,
+            RenderLayer::Foreground => self.foreground,
+        }
+    }
+}
+
 #[derive(Component, Serialize, Deserialize, Clone, Debug)]
 pub struct SpriteComponent {
     pub image: Option<SpriteImage>,
     pub mode: SpriteMode,
+    #[serde(default)]
+    pub layer: RenderLayer,
+    /// Ordre de dessin dans le calque, les plus grands sont dessinés par-dessus
+    #[serde(default)]
+    pub z_index: i32,
 }
 
 impl SpriteComponent {
     pub fn new(image: Option<SpriteImage>, mode: SpriteMode) -> Self {
-        SpriteComponent { image, mode }
+        SpriteComponent { image, mode, layer: RenderLayer::default(), z_index: 0 }
     }
 }
 
@@ -95,6 +152,8 @@ impl Default for SpriteComponent {
         SpriteComponent {
             image: None,
             mode: SpriteMode::Stretch,
+            layer: RenderLayer::default(),
+            z_index: 0,
         }
     }
 }
@@ -102,28 +161,33 @@ impl Default for SpriteComponent {
 pub struct RenderSystem<'a> {
     pub ctx: &'a mut Context,
     pub camera: &'a Camera,
+    pub resources_manager: &'a ResourcesManager,
+    /// `None` pour dessiner tous les calques sans parallaxe (éditeur)
+    pub parallax: Option<&'a ParallaxConfig>,
 }
 
-pub fn draw_sprite(ctx: &mut Context, camera: &Camera, spr_image: &SpriteImage, rect: Rect, mode: &SpriteMode) {
-    let image = spr_image.image.as_ref().expect("Sprite non chargé finch !");
+/// Paramètres de dessin de la partie `src` (normalisée) d'une image de `image_size` pixels dans le rectangle.
+fn sprite_draw_params(camera: &Camera, image_size: Vector2<f64>, src: graphics::Rect, rect: Rect, mode: &SpriteMode, parallax: f64) -> Vec<DrawParam> {
+    let (width, height) = (image_size.x * src.w as f64, image_size.y * src.h as f64);
 
     match mode {
         SpriteMode::Stretch => {
-            let scale = camera.world_size_to_screen(Vector2::new(rect.size.x as f64 / image.width() as f64, rect.size.y as f64 / image.height() as f64));
-            let pos_in_screen = camera.world_point_to_screen(rect.pos);
+            let scale = camera.world_size_to_screen(Vector2::new(rect.size.x as f64 / width, rect.size.y as f64 / height));
+            let pos_in_screen = camera.world_point_to_screen_parallax(rect.pos, parallax);
 
-            graphics::draw_ex(ctx, image, DrawParam { dest: Point2::new(pos_in_screen.x as f32, pos_in_screen.y as f32), scale: Point2::new(scale.x as f32, scale.y as f32), ..Default::default() }).unwrap();
+            vec![DrawParam { src, dest: Point2::new(pos_in_screen.x as f32, pos_in_screen.y as f32), scale: Point2::new(scale.x as f32, scale.y as f32), ..Default::default() }]
         }
         SpriteMode::Repeat { x, y } => {
-            let scale = camera.world_size_to_screen(Vector2::new(rect.size.x as f64 / x.get() as f64 / image.width() as f64, rect.size.y as f64 / y.get() as f64 / image.height() as f64));
+            let scale = camera.world_size_to_screen(Vector2::new(rect.size.x as f64 / x.get() as f64 / width, rect.size.y as f64 / y.get() as f64 / height));
 
-            let mut batch = SpriteBatch::new(image.clone());
+            let mut params = Vec::with_capacity((x.get() * y.get()) as usize);
 
             for x in 0..x.get() {
                 for y in 0..y.get() {
-                    let pos_in_screen = camera.world_point_to_screen(Point2::new(rect.pos.x, rect.pos.y + y as f64 * scale.y));
+                    let pos_in_screen = camera.world_point_to_screen_parallax(Point2::new(rect.pos.x, rect.pos.y + y as f64 * scale.y), parallax);
 
-                    batch.add(DrawParam {
+                    params.push(DrawParam {
+                        src,
                         dest: Point2::new(pos_in_screen.x as f32 + x as f32 * scale.x as f32, pos_in_screen.y as f32),
                         scale: Point2::new(scale.x as f32, scale.y as f32),
                         ..Default::default()
@@ -131,7 +195,7 @@ pub fn draw_sprite(ctx: &mut Context, camera: &Camera, spr_image: &SpriteImage,
                 }
             }
 
-            graphics::draw_ex(ctx, &batch, Default::default()).unwrap();
+            params
         }
         /*SpriteMode::RepeatSize(size) => {
             let scale = camera.world_size_to_screen(&Vector2::new(size.x.get() as f64 / image.width() as f64, size.y.get() as f64 / image.height() as f64));
@@ -158,18 +222,212 @@ pub fn draw_sprite(ctx: &mut Context, camera: &Camera, spr_image: &SpriteImage,
     }
 }
 
+/// Dessine la partie `src` (normalisée) de l'image dans le rectangle.
+pub fn draw_sprite(ctx: &mut Context, camera: &Camera, spr_image: &SpriteImage, src: graphics::Rect, rect: Rect, mode: &SpriteMode) {
+    let image = spr_image.image.as_ref().expect("Sprite non chargé finch !");
+
+    let mut batch = SpriteBatch::new(image.clone());
+
+    for param in sprite_draw_params(camera, Vector2::new(image.width() as f64, image.height() as f64), src, rect, mode, 1.) {
+        batch.add(param);
+    }
+
+    graphics::draw_ex(ctx, &batch, Default::default()).unwrap();
+}
+
+/// Répète l'image sur tout l'écran, décalée selon la position de la caméra et le facteur de parallaxe.
+pub fn draw_tiled_background(ctx: &mut Context, camera: &Camera, spr_image: &SpriteImage, parallax: f64) {
+    let image = match spr_image.image {
+        Some(ref image) => image,
+        None => return,
+    };
+
+    let tile = camera.world_size_to_screen(Vector2::new(image.width() as f64, image.height() as f64));
+
+    if tile.x < 1. || tile.y < 1. {
+        return;
+    }
+
+    // Le fond couvre tout l'écran quelle que soit la transformation courante, seule sa translation décale les tuiles
+    let transform = graphics::get_transform(ctx);
+    let origin = camera.world_point_to_screen_parallax(Point2::new(0., 0.), parallax) + Vector2::new(transform[(0, 3)] as f64, transform[(1, 3)] as f64);
+    let (offset_x, offset_y) = (origin.x % tile.x, origin.y % tile.y);
+    let start = Point2::new(if offset_x > 0. { offset_x - tile.x } else { offset_x }, if offset_y > 0. { offset_y - tile.y } else { offset_y });
+
+    let screen_size = ctx.screen_size();
+    let scale = Point2::new((tile.x / image.width() as f64) as f32, (tile.y / image.height() as f64) as f32);
+
+    let mut batch = SpriteBatch::new(image.clone());
+
+    let mut x = start.x;
+    while x < screen_size.x as f64 {
+        let mut y = start.y;
+        while y < screen_size.y as f64 {
+            batch.add(DrawParam { dest: Point2::new(x as f32, y as f32), scale, ..Default::default() });
+            y += tile.y;
+        }
+        x += tile.x;
+    }
+
+    graphics::push_transform(ctx, Some(graphics::Matrix4::identity()));
+    graphics::apply_transformations(ctx).unwrap();
+
+    graphics::draw_ex(ctx, &batch, Default::default()).unwrap();
+
+    graphics::pop_transform(ctx);
+    graphics::apply_transformations(ctx).unwrap();
+}
+
+/// Dessine les particules centrées sur leur position, avec la texture des réglages ou la texture par défaut.
+fn draw_particles(ctx: &mut Context, camera: &Camera, resources_manager: &ResourcesManager, settings: &ParticleSettings, particles: &[Particle]) {
+    if particles.is_empty() {
+        return;
+    }
+
+    let image = match settings.texture.as_ref().and_then(|path| resources_manager.get_texture(path)).or_else(|| resources_manager.get_particle_texture()) {
+        Some(image) => image,
+        None => return,
+    };
+
+    let mut batch = SpriteBatch::new(image.clone());
+
+    for particle in particles {
+        let (color, size) = particle.appearance(settings);
+
+        let pos_in_screen = camera.world_point_to_screen(particle.pos);
+        let size = camera.world_size_to_screen(Vector2::new(size, size));
+
+        batch.add(DrawParam {
+            dest: Point2::new(pos_in_screen.x as f32, pos_in_screen.y as f32),
+            offset: Point2::new(0.5, 0.5),
+            scale: Point2::new((size.x / image.width() as f64) as f32, (size.y / image.height() as f64) as f32),
+            color: Some(color),
+            ..Default::default()
+        });
+    }
+
+    graphics::draw_ex(ctx, &batch, Default::default()).unwrap();
+}
+
+/// Sprite à dessiner pendant l'image en cours.
+struct DrawItem<'s> {
+    layer: RenderLayer,
+    z_index: i32,
+    /// Texture du batch, `None` pour l'atlas
+    texture: Option<&'s Path>,
+    spr_image: &'s SpriteImage,
+    src: graphics::Rect,
+    rect: Rect,
+    mode: SpriteMode,
+}
+
+impl<'s> DrawItem<'s> {
+    fn new(resources_manager: &ResourcesManager, spr_image: &'s SpriteImage, src: graphics::Rect, rect: Rect, mode: SpriteMode, layer: RenderLayer, z_index: i32) -> Self {
+        let in_atlas = resources_manager.get_atlas().map_or(false, |atlas| atlas.get_region(&spr_image.path).is_some());
+
+        DrawItem { layer, z_index, texture: if in_atlas { None } else { Some(&spr_image.path) }, spr_image, src, rect, mode }
+    }
+}
+
+/// Sprites consécutifs de même texture regroupés, chaque batch est dessiné en un seul appel.
+#[derive(Default)]
+struct SpriteBatches {
+    batches: Vec<(Option<PathBuf>, SpriteBatch)>,
+}
+
+impl SpriteBatches {
+    fn add(&mut self, camera: &Camera, resources_manager: &ResourcesManager, item: &DrawItem, parallax: f64) {
+        let image = match item.spr_image.image {
+            Some(ref image) => image,
+            None => return,
+        };
+
+        let (image, src) = match item.texture {
+            None => {
+                let atlas = resources_manager.get_atlas().unwrap();
+                let region = atlas.get_region(&item.spr_image.path).unwrap();
+                let src = item.src;
+
+                (atlas.get_image(), graphics::Rect::new(region.x + src.x * region.w, region.y + src.y * region.h, src.w * region.w, src.h * region.h))
+            }
+            Some(_) => (image, item.src),
+        };
+
+        let same_texture = self.batches.last().map_or(false, |(texture, _)| texture.as_ref().map(|t| t.as_path()) == item.texture);
+
+        if !same_texture {
+            self.batches.push((item.texture.map(|t| t.to_owned()), SpriteBatch::new(image.clone())));
+        }
+
+        let batch = &mut self.batches.last_mut().unwrap().1;
+
+        for param in sprite_draw_params(camera, Vector2::new(image.width() as f64, image.height() as f64), src, item.rect, &item.mode, parallax) {
+            batch.add(param);
+        }
+    }
+
+    fn draw(&self, ctx: &mut Context) {
+        for (_, batch) in &self.batches {
+            graphics::draw_ex(ctx, batch, Default::default()).unwrap();
+        }
+    }
+}
+
+impl<'a> RenderSystem<'a> {
+    fn draw_items(&mut self, items: &[DrawItem]) {
+        let mut batches = SpriteBatches::default();
+
+        for item in items {
+            let parallax = self.parallax.map_or(1., |parallax| parallax.get_factor(item.layer));
+            batches.add(self.camera, self.resources_manager, item, parallax);
+        }
+
+        batches.draw(self.ctx);
+    }
+}
+
 impl<'a> System<'a> for RenderSystem<'a> {
     type SystemData = (
         ReadStorage<'a, RectComponent>,
         ReadStorage<'a, SpriteComponent>,
-        ReadStorage<'a, ActiveChunkMarker>
+        ReadStorage<'a, AnimationComponent>,
+        ReadStorage<'a, ParticleEmitterComponent>,
+        ReadStorage<'a, ActiveChunkMarker>,
+        Read<'a, ParticleBursts>,
     );
 
-    fn run(&mut self, (rects, sprites, active_chunks): Self::SystemData) {
-        for (rect, spr, _) in (&rects, &sprites, &active_chunks).join() {
+    fn run(&mut self, (rects, sprites, animations, emitters, active_chunks, bursts): Self::SystemData) {
+        let mut items = Vec::new();
+
+        for (rect, spr, _, _) in (&rects, &sprites, !&animations, &active_chunks).join() {
             if let Some(ref spr_image) = spr.image {
-                draw_sprite(self.ctx, self.camera, spr_image, rect.get_rect(), &spr.mode);
+                items.push(DrawItem::new(self.resources_manager, spr_image, graphics::Rect::one(), rect.get_rect(), spr.mode, spr.layer, spr.z_index));
             }
         }
+
+        for (rect, animation, _) in (&rects, &animations, &active_chunks).join() {
+            if let Some((spr_image, src)) = animation.source.get_frame(animation.current_frame()) {
+                items.push(DrawItem::new(self.resources_manager, spr_image, src, rect.get_rect(), SpriteMode::Stretch, animation.layer, animation.z_index));
+            }
+        }
+
+        // Le tri par texture à calque et profondeur égaux permet de regrouper un maximum de sprites par batch
+        items.sort_by(|a, b| (a.layer, a.z_index, a.texture).cmp(&(b.layer, b.z_index, b.texture)));
+
+        // Les particules sont dessinées avec le calque de jeu, sous le premier plan
+        let foreground_start = items.iter().position(|item| item.layer == RenderLayer::Foreground).unwrap_or(items.len());
+        let (items, foreground_items) = items.split_at(foreground_start);
+
+        self.draw_items(items);
+
+        for (emitter, _) in (&emitters, &active_chunks).join() {
+            draw_particles(self.ctx, self.camera, self.resources_manager, &emitter.settings, emitter.get_particles());
+        }
+
+        for group in bursts.get_groups() {
+            draw_particles(self.ctx, self.camera, self.resources_manager, &group.settings, group.get_particles());
+        }
+
+        self.draw_items(foreground_items);
     }
-}
\ No newline at end of file
+}
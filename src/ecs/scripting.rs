@@ -0,0 +1,402 @@
+use crate::{
+    ecs::{
+        actions::{
+            ActionComponent,
+            Actions,
+        },
+        chunk::ActiveChunkMarker,
+        physics::{
+            ContactsComponent,
+            PhysicsComponent,
+        },
+        rect::RectComponent,
+        triggers::TriggerComponent,
+    },
+    utils::{
+        constants,
+        input_manager::RefInputManager,
+    },
+};
+use dyon::{
+    self,
+    Dfn,
+    FnIndex,
+    Lt,
+    Module,
+    Runtime,
+    Type,
+    Variable,
+    Vec4,
+};
+use dyon::dyon_fn;
+use nalgebra::{
+    Point2,
+    Vector2,
+};
+use ron;
+use serde::{
+    Deserialize,
+    Serialize,
+};
+use specs::{
+    prelude::*,
+    saveload::{
+        Marker,
+        MarkerAllocator,
+        U64Marker,
+        U64MarkerAllocator,
+    },
+};
+use specs_derive::Component;
+use std::{
+    cell::RefCell,
+    collections::HashMap,
+    fs,
+    path::{
+        self,
+        Path,
+        PathBuf,
+    },
+    sync::Arc,
+    time::SystemTime,
+};
+
+/// Script dyon exécuté par l'entité, le chemin est relatif au dossier du niveau.
+///
+/// Les fonctions `on_update(id)`, `on_collide(id, other)`, `on_input(id, key, just_pressed)` et `on_trigger(id, other)` sont
+/// appelées si elles sont définies, `id` et `other` étant les marqueurs des entités.
+#[derive(Component, Serialize, Deserialize, Clone, Debug, Default)]
+pub struct ScriptComponent {
+    pub file: PathBuf,
+}
+
+impl ScriptComponent {
+    /// Chemin du script dans le dossier du niveau, `None` si le chemin est absolu ou remonte avec `..`.
+    pub fn resolve_path(&self, dir: &Path) -> Option<PathBuf> {
+        let confined = self.file.components().all(|c| match c {
+            path::Component::Normal(_) | path::Component::CurDir => true,
+            _ => false
+        });
+
+        if confined { Some(dir.join(&self.file)) } else { None }
+    }
+}
+
+/// Configuration et console des scripts du niveau.
+#[derive(Default)]
+pub struct Scripting {
+    pub dir: PathBuf,
+    /// Recharge les scripts modifiés sur le disque
+    pub hot_reload: bool,
+    pub console: Vec<String>,
+}
+
+impl Scripting {
+    pub fn log(&mut self, message: String) {
+        if self.console.last() == Some(&message) {
+            return;
+        }
+
+        self.console.push(message);
+
+        if self.console.len() > constants::SCRIPT_CONSOLE_MAX_LINES {
+            self.console.remove(0);
+        }
+    }
+}
+
+struct EntitySnapshot {
+    pos: Point2<f64>,
+    size: Vector2<u32>,
+    velocity: Vector2<f64>,
+    grounded: bool,
+}
+
+/// État accessible aux fonctions exposées aux scripts pendant l'exécution du `ScriptSystem`.
+#[derive(Default)]
+struct ScriptContext {
+    entities: HashMap<u64, EntitySnapshot>,
+    actions: Vec<(u64, Actions)>,
+    logs: Vec<String>,
+}
+
+thread_local! {
+    static SCRIPT_CONTEXT: RefCell<ScriptContext> = RefCell::new(ScriptContext::default());
+}
+
+fn with_context<R, F: FnOnce(&mut ScriptContext) -> R>(f: F) -> R {
+    SCRIPT_CONTEXT.with(|c| f(&mut c.borrow_mut()))
+}
+
+fn vec4(x: f64, y: f64) -> Vec4 { Vec4([x as f32, y as f32, 0., 0.]) }
+
+/// Identifiant de marqueur passé par un script, les identifiants négatifs ou non entiers sont refusés.
+fn entity_id(id: f64) -> Option<u64> {
+    if id >= 0. && id.fract() == 0. {
+        Some(id as u64)
+    } else {
+        with_context(|c| c.logs.push(format!("Identifiant d'entité invalide : {}", id)));
+        None
+    }
+}
+
+fn with_entity<R, F: FnOnce(&EntitySnapshot) -> R>(id: f64, f: F) -> Option<R> {
+    entity_id(id).and_then(|id| with_context(|c| c.entities.get(&id).map(f)))
+}
+
+fn push_action(id: f64, action: Actions) {
+    if let Some(id) = entity_id(id) {
+        with_context(|c| c.actions.push((id, action)));
+    }
+}
+
+dyon_fn!{fn console(message: String) {
+    with_context(|c| c.logs.push(message));
+}}
+
+dyon_fn!{fn entity_exists(id: f64) -> bool {
+    with_entity(id, |_| ()).is_some()
+}}
+
+dyon_fn!{fn entity_pos(id: f64) -> Vec4 {
+    with_entity(id, |e| vec4(e.pos.x, e.pos.y)).unwrap_or(vec4(0., 0.))
+}}
+
+dyon_fn!{fn entity_size(id: f64) -> Vec4 {
+    with_entity(id, |e| vec4(e.size.x as f64, e.size.y as f64)).unwrap_or(vec4(0., 0.))
+}}
+
+dyon_fn!{fn entity_velocity(id: f64) -> Vec4 {
+    with_entity(id, |e| vec4(e.velocity.x, e.velocity.y)).unwrap_or(vec4(0., 0.))
+}}
+
+dyon_fn!{fn entity_grounded(id: f64) -> bool {
+    with_entity(id, |e| e.grounded).unwrap_or(false)
+}}
+
+dyon_fn!{fn set_pos(id: f64, pos: Vec4) {
+    if let Some(current) = with_entity(id, |e| e.pos) {
+        push_action(id, Actions::Move(Vector2::new(pos.0[0] as f64 - current.x, pos.0[1] as f64 - current.y)));
+    }
+}}
+
+dyon_fn!{fn physics_move(id: f64, mv: Vec4) {
+    push_action(id, Actions::PhysicsMove(Vector2::new(mv.0[0] as f64, mv.0[1] as f64)));
+}}
+
+dyon_fn!{fn physics_jump(id: f64, height: f64) {
+    push_action(id, Actions::PhysicsJump(height.max(0.) as u32));
+}}
+
+dyon_fn!{fn physics_impulse(id: f64, impulse: Vec4) {
+    push_action(id, Actions::PhysicsImpulse(Vector2::new(impulse.0[0] as f64, impulse.0[1] as f64)));
+}}
+
+dyon_fn!{fn do_action(id: f64, action: String) {
+    match ron::de::from_str::<Actions>(&action) {
+        Ok(action) => push_action(id, action),
+        Err(err) => with_context(|c| c.logs.push(format!("Action \"{}\" invalide : {}", action, err))),
+    }
+}}
+
+fn add_api(module: &mut Module) {
+    macro_rules! add_fn {
+        ($name:ident, [$($ty:expr),*], $ret:expr) => {
+            let tys = vec![$($ty),*];
+            module.add(Arc::new(stringify!($name).into()), $name, Dfn { lts: vec![Lt::Default; tys.len()], tys, ret: $ret });
+        };
+    }
+
+    add_fn!(console, [Type::Str], Type::Void);
+    add_fn!(entity_exists, [Type::F64], Type::Bool);
+    add_fn!(entity_pos, [Type::F64], Type::Vec4);
+    add_fn!(entity_size, [Type::F64], Type::Vec4);
+    add_fn!(entity_velocity, [Type::F64], Type::Vec4);
+    add_fn!(entity_grounded, [Type::F64], Type::Bool);
+    add_fn!(set_pos, [Type::F64, Type::Vec4], Type::Void);
+    add_fn!(physics_move, [Type::F64, Type::Vec4], Type::Void);
+    add_fn!(physics_jump, [Type::F64, Type::F64], Type::Void);
+    add_fn!(physics_impulse, [Type::F64, Type::Vec4], Type::Void);
+    add_fn!(do_action, [Type::F64, Type::Str], Type::Void);
+}
+
+const HOOKS: [&str; 4] = ["on_update", "on_collide", "on_input", "on_trigger"];
+
+struct LoadedScript {
+    /// `None` si le script n'a pas pu être chargé
+    module: Option<Arc<Module>>,
+    hooks: Vec<&'static str>,
+    modified: Option<SystemTime>,
+}
+
+impl LoadedScript {
+    fn load(path: &PathBuf, scripting: &mut Scripting) -> Self {
+        let modified = fs::metadata(path).and_then(|m| m.modified()).ok();
+
+        let loaded = fs::read_to_string(path).map_err(|err| err.to_string()).and_then(|source| {
+            let mut module = Module::new();
+            add_api(&mut module);
+
+            dyon::load_str(&path.to_string_lossy(), Arc::new(source), &mut module)?;
+
+            let hooks = HOOKS.iter().filter(|hook| match module.find_function(&Arc::new(hook.to_string()), 0) {
+                FnIndex::Loaded(_) => true,
+                _ => false
+            }).cloned().collect();
+
+            Ok((module, hooks))
+        });
+
+        match loaded {
+            Ok((module, hooks)) => LoadedScript { module: Some(Arc::new(module)), hooks, modified },
+            Err(err) => {
+                scripting.log(format!("Impossible de charger le script {:?} : {}", path, err));
+                LoadedScript { module: None, hooks: vec![], modified }
+            }
+        }
+    }
+
+    fn has_hook(&self, hook: &str) -> bool { self.hooks.contains(&hook) }
+}
+
+/// Exécute les scripts des entités, doit être ajouté comme système local au thread (`with_thread_local`).
+pub struct ScriptSystem {
+    pub input_manager: RefInputManager,
+    runtime: Runtime,
+    scripts: HashMap<PathBuf, LoadedScript>,
+    /// Scripts dont le chemin sort du dossier du niveau, déjà signalés dans la console
+    rejected: Vec<PathBuf>,
+    ticks: u32,
+}
+
+impl ScriptSystem {
+    pub fn new(input_manager: RefInputManager) -> Self {
+        ScriptSystem { input_manager, runtime: Runtime::new(), scripts: HashMap::new(), rejected: vec![], ticks: 0 }
+    }
+
+    fn reload_modified(&mut self, scripting: &mut Scripting) {
+        let modified: Vec<PathBuf> = self.scripts.iter()
+            .filter(|(path, script)| fs::metadata(path).and_then(|m| m.modified()).ok() != script.modified)
+            .map(|(path, _)| path.clone())
+            .collect();
+
+        for path in modified {
+            scripting.log(format!("Script {:?} rechargé", path));
+            let script = LoadedScript::load(&path, scripting);
+            self.scripts.insert(path, script);
+        }
+    }
+
+    fn call(runtime: &mut Runtime, script: &LoadedScript, path: &PathBuf, hook: &'static str, args: &[Variable], scripting: &mut Scripting) {
+        if let Some(ref module) = script.module {
+            if script.has_hook(hook) {
+                if let Err(err) = runtime.call_str(hook, args, module) {
+                    scripting.log(format!("Erreur dans {} du script {:?} : {}", hook, path, err));
+                }
+            }
+        }
+    }
+}
+
+impl<'a> System<'a> for ScriptSystem {
+    type SystemData = (
+        Entities<'a>,
+        Write<'a, Scripting>,
+        Read<'a, U64MarkerAllocator>,
+        ReadStorage<'a, ScriptComponent>,
+        ReadStorage<'a, RectComponent>,
+        ReadStorage<'a, PhysicsComponent>,
+        ReadStorage<'a, ContactsComponent>,
+        ReadStorage<'a, TriggerComponent>,
+        ReadStorage<'a, U64Marker>,
+        WriteStorage<'a, ActionComponent>,
+        ReadStorage<'a, ActiveChunkMarker>,
+    );
+
+    fn run(&mut self, (entities, mut scripting, u64_marker_allocator, scripts, rects, physics, contacts, triggers, markers, mut actions, active_chunk): Self::SystemData) {
+        self.ticks += 1;
+
+        if scripting.hot_reload && self.ticks % constants::SCRIPT_HOT_RELOAD_TICKS == 0 {
+            self.reload_modified(&mut scripting);
+        }
+
+        with_context(|c| {
+            c.entities = (&markers, &rects).join().map(|(marker, rect)| {
+                let ent = u64_marker_allocator.retrieve_entity_internal(marker.id());
+
+                (marker.id(), EntitySnapshot {
+                    pos: rect.pos(),
+                    size: rect.size(),
+                    velocity: ent.and_then(|e| physics.get(e)).map_or(Vector2::new(0., 0.), |p| p.velocity),
+                    grounded: ent.and_then(|e| contacts.get(e)).map_or(false, |c| c.is_grounded()),
+                })
+            }).collect();
+        });
+
+        let pressed_keys = self.input_manager.lock().unwrap().get_pressed_keys();
+
+        let id_of = |e: Entity| markers.get(e).map(|m| m.id() as f64);
+
+        for (ent, script, _) in (&*entities, &scripts, &active_chunk).join() {
+            // Les hooks reçoivent l'identifiant du marqueur, une entité sans marqueur ne peut pas être désignée par son script
+            let id = match id_of(ent) {
+                Some(id) => Variable::f64(id),
+                None => continue,
+            };
+
+            let path = match script.resolve_path(&scripting.dir) {
+                Some(path) => path,
+                None => {
+                    if !self.rejected.contains(&script.file) {
+                        scripting.log(format!("Script {:?} refusé : le chemin doit être relatif au dossier du niveau", script.file));
+                        self.rejected.push(script.file.clone());
+                    }
+                    continue;
+                }
+            };
+
+            if !self.scripts.contains_key(&path) {
+                let loaded = LoadedScript::load(&path, &mut scripting);
+                self.scripts.insert(path.clone(), loaded);
+            }
+
+            let loaded = &self.scripts[&path];
+            let runtime = &mut self.runtime;
+
+            Self::call(runtime, loaded, &path, "on_update", &[id.clone()], &mut scripting);
+
+            if let Some(contacts) = contacts.get(ent) {
+                for other in contacts.contacts.iter().filter_map(|contact| id_of(contact.entity)) {
+                    Self::call(runtime, loaded, &path, "on_collide", &[id.clone(), Variable::f64(other)], &mut scripting);
+                }
+            }
+
+            for (key, just_pressed) in pressed_keys.iter() {
+                Self::call(runtime, loaded, &path, "on_input", &[id.clone(), Variable::Str(Arc::new(key.name())), Variable::bool(*just_pressed)], &mut scripting);
+            }
+
+            if let Some(trigger) = triggers.get(ent) {
+                for other in trigger.get_entered().iter().filter_map(|other| id_of(*other)) {
+                    Self::call(runtime, loaded, &path, "on_trigger", &[id.clone(), Variable::f64(other)], &mut scripting);
+                }
+            }
+        }
+
+        let (script_actions, logs) = with_context(|c| {
+            c.entities.clear();
+            (c.actions.drain(..).collect::<Vec<_>>(), c.logs.drain(..).collect::<Vec<_>>())
+        });
+
+        for log in logs {
+            scripting.log(log);
+        }
+
+        for (id, action) in script_actions {
+            if let Some(target) = u64_marker_allocator.retrieve_entity_internal(id) {
+                if entities.is_alive(target) {
+                    ActionComponent::push_action(&mut actions, target, action);
+                }
+            }
+        }
+    }
+}
@@ -1,109 +1,593 @@
 use crate::ecs::{
+    ai::AiComponent,
+    animation::AnimationComponent,
+    checkpoints::CheckpointComponent,
+    collectibles::{
+        CollectibleComponent,
+        DoorComponent,
+        InventoryComponent,
+    },
+    health::{
+        DamageComponent,
+        HealthComponent,
+    },
     inputs::InputComponent,
+    lighting::LightComponent,
+    particles::ParticleEmitterComponent,
+    path::PathComponent,
     physics::PhysicsComponent,
     rect::RectComponent,
     render::SpriteComponent,
+    scripting::ScriptComponent,
+    triggers::TriggerComponent,
+    variables::VariablesComponent,
+};
+use bincode;
+use flate2::{
+    Compression,
+    read::GzDecoder,
+    write::GzEncoder,
 };
 use ron;
+use serde::{
+    Deserialize,
+    Serialize,
+};
 use specs::{
-    error::NoError,
     prelude::*,
     saveload::{
-        DeserializeComponents,
-        SerializeComponents,
+        Marker,
+        MarkerAllocator,
         U64Marker,
         U64MarkerAllocator,
     },
 };
 use std::{
+    collections::HashSet,
+    error::Error,
     fmt,
-    io,
+    io::{
+        self,
+        Read,
+        Write,
+    },
+    str::FromStr,
 };
 
+/// Version du format écrite par `save_world`.
+/// - 0 : liste d'entités de `SerializeComponents` avec (rect, sprite, input, physics), sans enveloppe
+/// - 1 : enveloppe avec la version, les métadonnées et les composants par nom
+///
+/// bincode ne tolère aucun champ ajouté : toute modification de la disposition d'un composant doit incrémenter la version.
+pub const WORLD_FORMAT_VERSION: u32 = 1;
+
+/// Début des fichiers binaires, absent des fichiers RON qui commencent par `[` ou `(`.
+const BINARY_MAGIC: &[u8] = b"RPWB";
+
+/// Encodage du monde choisi dans la configuration du niveau.
+/// Le RON reste lisible et comparable dans git, le binaire (bincode compressé) est plus compact et plus rapide à charger.
+/// Le binaire dépend de la disposition exacte des composants : il est régénéré depuis le RON après une mise à jour du jeu.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+pub enum WorldEncoding {
+    Ron,
+    Binary,
+}
+
+impl Default for WorldEncoding {
+    fn default() -> Self { WorldEncoding::Ron }
+}
+
+impl FromStr for WorldEncoding {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "ron" => Ok(WorldEncoding::Ron),
+            "binaire" => Ok(WorldEncoding::Binary),
+            _ => Err(format!("encodage inconnu : {} (ron ou binaire attendu)", s)),
+        }
+    }
+}
+
 #[derive(Debug)]
-enum Combined {
-    Ron(ron::ser::Error),
+pub enum WorldError {
+    Io(io::Error),
+    Serialize(ron::ser::Error),
+    Deserialize(ron::de::Error),
+    Binary(bincode::Error),
+    /// Le début du fichier ne correspond à aucune version connue
+    UnknownFormat,
+    /// Fichier enregistré par une version plus récente du jeu
+    UnsupportedVersion(u32),
+    /// Composant enregistré par une version plus récente du jeu, il serait perdu au prochain enregistrement
+    UnknownComponent(String),
 }
 
-impl fmt::Display for Combined {
+impl fmt::Display for WorldError {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        match *self {
-            Combined::Ron(ref e) => write!(f, "{}", e),
+        match self {
+            WorldError::Io(err) => write!(f, "impossible d'accéder au fichier du monde : {}", err),
+            WorldError::Serialize(err) => write!(f, "impossible d'enregistrer le monde : {}", err),
+            WorldError::Deserialize(err) => write!(f, "fichier du monde invalide : {}", err),
+            WorldError::Binary(err) => write!(f, "fichier binaire du monde invalide, à régénérer depuis le RON : {}", err),
+            WorldError::UnknownFormat => write!(f, "format du fichier du monde inconnu"),
+            WorldError::UnsupportedVersion(version) => write!(f, "le format {} du monde n'est pas pris en charge (format actuel : {})", version, WORLD_FORMAT_VERSION),
+            WorldError::UnknownComponent(name) => write!(f, "composant inconnu : {}", name),
         }
     }
 }
 
-impl From<ron::ser::Error> for Combined {
-    fn from(x: ron::ser::Error) -> Self {
-        Combined::Ron(x)
+impl Error for WorldError {}
+
+impl From<io::Error> for WorldError {
+    fn from(err: io::Error) -> Self { WorldError::Io(err) }
+}
+
+impl From<ron::ser::Error> for WorldError {
+    fn from(err: ron::ser::Error) -> Self { WorldError::Serialize(err) }
+}
+
+impl From<ron::de::Error> for WorldError {
+    fn from(err: ron::de::Error) -> Self { WorldError::Deserialize(err) }
+}
+
+impl From<bincode::Error> for WorldError {
+    fn from(err: bincode::Error) -> Self { WorldError::Binary(err) }
+}
+
+/// Informations sur le niveau enregistrées avec le monde.
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct WorldMetadata {
+    pub name: String,
+    pub author: String,
+    /// Version du jeu qui a enregistré le niveau
+    pub game_version: String,
+    pub entities: usize,
+}
+
+fn collect_components<C: Component + Clone>(markers: &ReadStorage<U64Marker>, storage: &ReadStorage<C>) -> Vec<(u64, C)> {
+    let mut components: Vec<(u64, C)> = (markers, storage).join().map(|(marker, comp)| (marker.id(), comp.clone())).collect();
+    components.sort_by_key(|(id, _)| *id);
+
+    components
+}
+
+fn insert_components<C: Component>(world: &World, components: Vec<(u64, C)>) {
+    let entities = world.entities();
+    let mut allocator = world.write_resource::<U64MarkerAllocator>();
+    let mut markers = world.write_storage::<U64Marker>();
+    let mut storage = world.write_storage::<C>();
+
+    for (id, comp) in components {
+        let entity = allocator.retrieve_entity(U64Marker(id), &mut markers, &entities);
+        storage.insert(entity, comp).unwrap();
+    }
+}
+
+fn push_component<C>(components: &mut Vec<(u64, C)>, id: u64, comp: Option<C>) {
+    if let Some(comp) = comp {
+        components.push((id, comp));
+    }
+}
+
+macro_rules! impl_world_components {
+    ($($name:ident: $comp:ty),*) => {
+        /// Composants du monde, chacun associé au marqueur de son entité.
+        /// Un composant absent du fichier RON reste vide, ajouter un composant ne casse donc pas les niveaux existants.
+        /// Les listes vides sont tout de même écrites, bincode ne sait pas lire un champ omis.
+        #[derive(Serialize, Deserialize, Default)]
+        struct WorldComponents {
+            $(
+                #[serde(default)]
+                $name: Vec<(u64, $comp)>,
+            )*
+        }
+
+        const COMPONENTS_NAMES: &[&str] = &[$(stringify!($name)),*];
+
+        impl WorldComponents {
+            fn from_world(world: &World) -> Self {
+                let markers = world.read_storage::<U64Marker>();
+
+                WorldComponents { $($name: collect_components(&markers, &world.read_storage::<$comp>())),* }
+            }
+
+            fn names(&self) -> Vec<String> {
+                let mut names = vec![];
+                $(
+                    if !self.$name.is_empty() {
+                        names.push(stringify!($name).to_owned());
+                    }
+                )*
+                names
+            }
+
+            fn entities_count(&self) -> usize {
+                let mut ids = HashSet::new();
+                $(ids.extend(self.$name.iter().map(|(id, _)| *id));)*
+                ids.len()
+            }
+
+            fn insert_into(self, world: &World) {
+                $(insert_components(world, self.$name);)*
+            }
+        }
+    };
+}
+
+impl_world_components!(
+    rect: RectComponent,
+    sprite: SpriteComponent,
+    input: InputComponent,
+    physics: PhysicsComponent,
+    path: PathComponent,
+    trigger: TriggerComponent,
+    checkpoint: CheckpointComponent,
+    health: HealthComponent,
+    damage: DamageComponent,
+    collectible: CollectibleComponent,
+    inventory: InventoryComponent,
+    door: DoorComponent,
+    ai: AiComponent,
+    variables: VariablesComponent,
+    script: ScriptComponent,
+    animation: AnimationComponent,
+    light: LightComponent,
+    particle_emitter: ParticleEmitterComponent
+);
+
+/// Contenu de `world.dat` depuis la version 1.
+#[derive(Serialize, Deserialize)]
+struct WorldFile {
+    version: u32,
+    metadata: WorldMetadata,
+    /// Composants présents dans `data`, vérifiés avant le chargement
+    components: Vec<String>,
+    data: WorldComponents,
+}
+
+/// Entité enregistrée par `SerializeComponents` de specs, dans la version 0.
+#[derive(Deserialize)]
+struct LegacyEntity<C> {
+    marker: U64Marker,
+    components: C,
+}
+
+type WorldV0 = Vec<LegacyEntity<(Option<RectComponent>, Option<SpriteComponent>, Option<InputComponent>, Option<PhysicsComponent>)>>;
+
+fn migrate_v0(world: WorldV0) -> WorldComponents {
+    let mut components = WorldComponents::default();
+
+    for entity in world {
+        let id = entity.marker.id();
+        let (rect, sprite, input, physics) = entity.components;
+
+        push_component(&mut components.rect, id, rect);
+        push_component(&mut components.sprite, id, sprite);
+        push_component(&mut components.input, id, input);
+        push_component(&mut components.physics, id, physics);
     }
+
+    components
 }
 
-impl From<NoError> for Combined {
-    fn from(e: NoError) -> Self {
-        match e {}
+/// La version 0 n'a pas d'enveloppe, c'est une liste d'entités.
+fn detect_version(content: &str) -> Result<u32, WorldError> {
+    let content = content.trim_start();
+
+    if content.starts_with('[') {
+        return Ok(0);
+    }
+
+    if !content.starts_with('(') {
+        return Err(WorldError::UnknownFormat);
+    }
+
+    let content = content[1..].trim_start();
+
+    // L'enveloppe commence toujours par la version, lue avant le reste du fichier dont le format en dépend
+    if content.starts_with("version") {
+        let value = content["version".len()..].trim_start().trim_start_matches(':').trim_start();
+        let digits: String = value.chars().take_while(|c| c.is_ascii_digit()).collect();
+
+        return digits.parse().map_err(|_| WorldError::UnknownFormat);
     }
+
+    Err(WorldError::UnknownFormat)
 }
 
-pub struct SerializeSystem<W: io::Write> {
-    pub writer: W
+fn write_world_file<W: Write>(name: &str, author: &str, data: WorldComponents, encoding: WorldEncoding, mut writer: W) -> Result<WorldMetadata, WorldError> {
+    let metadata = WorldMetadata { name: name.to_owned(), author: author.to_owned(), game_version: env!("CARGO_PKG_VERSION").to_owned(), entities: data.entities_count() };
+
+    let file = WorldFile { version: WORLD_FORMAT_VERSION, metadata: metadata.clone(), components: data.names(), data };
+
+    match encoding {
+        WorldEncoding::Ron => {
+            let content = ron::ser::to_string_pretty(&file, Default::default())?;
+            writer.write_all(content.as_bytes())?;
+        }
+        WorldEncoding::Binary => {
+            // La version est écrite hors de la partie compressée, lue avant de choisir la disposition du contenu
+            writer.write_all(BINARY_MAGIC)?;
+            bincode::serialize_into(&mut writer, &WORLD_FORMAT_VERSION)?;
+
+            let mut encoder = GzEncoder::new(writer, Compression::default());
+            bincode::serialize_into(&mut encoder, &file)?;
+            encoder.finish()?;
+        }
+    }
+
+    Ok(metadata)
 }
 
-impl<'a, W: io::Write> System<'a> for SerializeSystem<W> {
-    type SystemData = (
-        Entities<'a>,
-        ReadStorage<'a, RectComponent>,
-        ReadStorage<'a, SpriteComponent>,
-        ReadStorage<'a, InputComponent>,
-        ReadStorage<'a, PhysicsComponent>,
-        ReadStorage<'a, U64Marker>,
-    );
+/// Taille de la version écrite par bincode après `BINARY_MAGIC`.
+const BINARY_VERSION_SIZE: usize = 4;
+
+fn read_binary_world_file(bytes: &[u8]) -> Result<(u32, WorldMetadata, WorldComponents), WorldError> {
+    if bytes.len() < BINARY_VERSION_SIZE {
+        return Err(WorldError::UnknownFormat);
+    }
+
+    let version: u32 = bincode::deserialize(&bytes[..BINARY_VERSION_SIZE])?;
+
+    let mut content = vec![];
+    GzDecoder::new(&bytes[BINARY_VERSION_SIZE..]).read_to_end(&mut content)?;
+
+    // Une disposition binaire modifiée garde sa version ici avec sa migration, comme les versions RON.
+    // Sans migration, le niveau est rechargé depuis son RON qui tolère les changements de disposition.
+    let file: WorldFile = match version {
+        WORLD_FORMAT_VERSION => bincode::deserialize(&content)?,
+        version => return Err(WorldError::UnsupportedVersion(version)),
+    };
 
-    fn run(&mut self, (ents, rects, sprites, inputs, physics, markers): Self::SystemData) {
-        let mut ser = ron::ser::Serializer::new(Some(Default::default()), true);
-        SerializeComponents::<NoError, U64Marker>::serialize(
-            &(&rects, &sprites, &inputs, &physics),
-            &ents,
-            &markers,
-            &mut ser,
-        ).unwrap_or_else(|e| eprintln!("Error: {}", e));
-        // TODO: Specs should return an error which combines serialization
-        // and component errors.
+    Ok((version, file.metadata, file.data))
+}
 
-        self.writer.write_all(ser.into_output_string().as_bytes()).expect("Impossible d'enregistrer le niveau !");
+/// Lit un fichier RON ou binaire, les fichiers RON des versions précédentes sont migrés vers la version actuelle.
+fn read_world_file(bytes: &[u8]) -> Result<(u32, WorldMetadata, WorldComponents), WorldError> {
+    if bytes.starts_with(BINARY_MAGIC) {
+        return read_binary_world_file(&bytes[BINARY_MAGIC.len()..]);
     }
+
+    let content = String::from_utf8_lossy(bytes);
+
+    let version = detect_version(&content)?;
+
+    let (metadata, data) = match version {
+        0 => (WorldMetadata::default(), migrate_v0(ron::de::from_str(&content)?)),
+        WORLD_FORMAT_VERSION => {
+            let file: WorldFile = ron::de::from_str(&content)?;
+
+            if let Some(unknown) = file.components.iter().find(|name| !COMPONENTS_NAMES.contains(&name.as_str())) {
+                return Err(WorldError::UnknownComponent(unknown.clone()));
+            }
+
+            (file.metadata, file.data)
+        }
+        version => return Err(WorldError::UnsupportedVersion(version)),
+    };
+
+    Ok((version, metadata, data))
+}
+
+pub fn save_world<W: Write>(world: &World, name: &str, author: &str, encoding: WorldEncoding, writer: W) -> Result<WorldMetadata, WorldError> {
+    write_world_file(name, author, WorldComponents::from_world(world), encoding, writer)
+}
+
+/// Charge les entités du fichier, RON ou binaire, dans le monde.
+/// Retourne la version du fichier lu et ses métadonnées, vides dans la version 0.
+pub fn load_world<R: Read>(world: &World, mut reader: R) -> Result<(u32, WorldMetadata), WorldError> {
+    let mut bytes = vec![];
+    reader.read_to_end(&mut bytes)?;
+
+    let (version, metadata, data) = read_world_file(&bytes)?;
+
+    // Le monde n'est modifié qu'une fois le fichier entièrement lu, une erreur ne laisse pas de monde à moitié chargé
+    data.insert_into(world);
+
+    Ok((version, metadata))
 }
 
-pub struct DeserializeSystem<R: io::Read> {
-    pub reader: R
+/// Réécrit un fichier du monde avec l'encodage donné, sans passer par un monde specs.
+/// Les fichiers des versions précédentes sont migrés au passage.
+pub fn convert_world<R: Read, W: Write>(mut reader: R, name: &str, author: &str, encoding: WorldEncoding, writer: W) -> Result<WorldMetadata, WorldError> {
+    let mut bytes = vec![];
+    reader.read_to_end(&mut bytes)?;
+
+    let (_, _, data) = read_world_file(&bytes)?;
+
+    write_world_file(name, author, data, encoding, writer)
 }
 
-impl<'a, R: io::Read> System<'a> for DeserializeSystem<R> {
-    type SystemData = (
-        Entities<'a>,
-        Write<'a, U64MarkerAllocator>,
-        WriteStorage<'a, RectComponent>,
-        WriteStorage<'a, SpriteComponent>,
-        WriteStorage<'a, InputComponent>,
-        WriteStorage<'a, PhysicsComponent>,
-        WriteStorage<'a, U64Marker>,
-    );
+#[cfg(test)]
+mod tests {
+    use crate::{
+        ecs::{
+            self,
+            actions::Actions,
+            inputs::InputComponent,
+            physics::{
+                BodyType,
+                PhysicsComponent,
+            },
+            rect::RectComponent,
+            render::SpriteComponent,
+        },
+        utils::math::Rect,
+    };
+    use ggez::event::Keycode;
+    use specs::{
+        prelude::*,
+        saveload::{
+            MarkerAllocator,
+            U64MarkerAllocator,
+        },
+    };
+    use std::path::Path;
+    use super::*;
+
+    /// Monde enregistré par le `SerializeSystem` d'origine : un joueur et un sol.
+    const BASELINE_WORLD: &str = r#"[
+    (
+        marker: (1),
+        components: (
+            Some((
+                rect: (
+                    pos: [100.0, 50.0],
+                    size: [50, 80],
+                ),
+            )),
+            Some((
+                image: Some((
+                    path: "/sprites/joueur.png",
+                )),
+                mode: Stretch,
+            )),
+            Some((
+                input_actions: [
+                    (1073741906, true, PhysicsJump(150)),
+                ],
+            )),
+            Some((
+                body_type: Dynamic(
+                    apply_gravity: true,
+                ),
+                next_physics_step: [],
+            )),
+        ),
+    ),
+    (
+        marker: (2),
+        components: (
+            Some((
+                rect: (
+                    pos: [0.0, 300.0],
+                    size: [2000, 50],
+                ),
+            )),
+            None,
+            None,
+            Some((
+                body_type: Static,
+                next_physics_step: [],
+            )),
+        ),
+    ),
+]"#;
+
+    fn load_baseline_world() -> World {
+        let (world, _) = ecs::create_default_world();
+
+        let (version, metadata) = load_world(&world, BASELINE_WORLD.as_bytes()).unwrap();
 
-    fn run(&mut self, (ent, mut alloc, rects, sprites, inputs, physics, mut markers): Self::SystemData) {
-        use ron::de::Deserializer;
+        assert_eq!(version, 0);
+        assert_eq!(metadata.entities, 0);
 
-        let mut content: Vec<u8> = vec![];
+        world
+    }
+
+    fn entity(world: &World, id: u64) -> Entity {
+        world.read_resource::<U64MarkerAllocator>().retrieve_entity_internal(id).unwrap()
+    }
+
+    fn assert_rect(rect: Rect, x: f64, y: f64, w: u32, h: u32) {
+        assert_eq!((rect.pos.x, rect.pos.y, rect.size.x, rect.size.y), (x, y, w, h));
+    }
+
+    fn assert_baseline_components(world: &World) {
+        let (player, floor) = (entity(world, 1), entity(world, 2));
+
+        let rects = world.read_storage::<RectComponent>();
+        assert_rect(rects.get(player).unwrap().get_rect(), 100., 50., 50, 80);
+        assert_rect(rects.get(floor).unwrap().get_rect(), 0., 300., 2000, 50);
+
+        let sprites = world.read_storage::<SpriteComponent>();
+        let sprite = sprites.get(player).unwrap();
+        assert_eq!(sprite.image.as_ref().map(|image| image.get_path().as_path()), Some(Path::new("/sprites/joueur.png")));
+        assert_eq!(sprite.z_index, 0);
+        assert!(!sprites.contains(floor));
+
+        let inputs = world.read_storage::<InputComponent>();
+        match inputs.get(player).unwrap().input_actions.as_slice() {
+            [(key, true, Actions::PhysicsJump(150))] => assert_eq!(*key, Keycode::Up as i32),
+            _ => panic!("entrée du joueur mal migrée"),
+        }
+        assert!(!inputs.contains(floor));
+
+        let physics = world.read_storage::<PhysicsComponent>();
+        match physics.get(player).unwrap().body_type {
+            BodyType::Dynamic { apply_gravity } => assert!(apply_gravity),
+            BodyType::Static => panic!("le joueur doit rester dynamique"),
+        }
+        match physics.get(floor).unwrap().body_type {
+            BodyType::Static => {}
+            BodyType::Dynamic { .. } => panic!("le sol doit rester statique"),
+        }
+    }
+
+    #[test]
+    fn baseline_world_is_migrated() {
+        let world = load_baseline_world();
+
+        assert_eq!(world.entities().join().count(), 2);
+        assert_baseline_components(&world);
+    }
+
+    #[test]
+    fn migrated_world_is_saved_in_current_format() {
+        let world = load_baseline_world();
+
+        let mut saved = vec![];
+        let metadata = save_world(&world, "base", "tests", WorldEncoding::Ron, &mut saved).unwrap();
+        assert_eq!(metadata.entities, 2);
+        assert_eq!(detect_version(&String::from_utf8_lossy(&saved)).unwrap(), WORLD_FORMAT_VERSION);
+
+        let (reloaded, _) = ecs::create_default_world();
+        let (version, metadata) = load_world(&reloaded, saved.as_slice()).unwrap();
+
+        assert_eq!(version, WORLD_FORMAT_VERSION);
+        assert_eq!(metadata.name, "base");
+        assert_baseline_components(&reloaded);
+    }
+
+    #[test]
+    fn ron_and_binary_round_trip() {
+        let world = load_baseline_world();
+
+        let mut content = vec![];
+        save_world(&world, "base", "tests", WorldEncoding::Ron, &mut content).unwrap();
 
-        self.reader.read_to_end(&mut content).unwrap();
+        let mut binary = vec![];
+        convert_world(content.as_slice(), "base", "tests", WorldEncoding::Binary, &mut binary).unwrap();
+        assert!(binary.starts_with(BINARY_MAGIC));
 
-        if let Ok(mut de) = Deserializer::from_bytes(&content) {
-            DeserializeComponents::<Combined, _>::deserialize(
-                &mut (rects, sprites, inputs, physics),
-                &ent,
-                &mut markers,
-                &mut alloc,
-                &mut de,
-            ).unwrap_or_else(|e| eprintln!("Error: {}", e));
+        let (reloaded, _) = ecs::create_default_world();
+        let (version, metadata) = load_world(&reloaded, binary.as_slice()).unwrap();
+
+        assert_eq!(version, WORLD_FORMAT_VERSION);
+        assert_eq!(metadata.entities, 2);
+        assert_baseline_components(&reloaded);
+
+        let mut converted = vec![];
+        convert_world(binary.as_slice(), "base", "tests", WorldEncoding::Ron, &mut converted).unwrap();
+        assert_eq!(String::from_utf8(converted).unwrap(), String::from_utf8(content).unwrap());
+    }
+
+    #[test]
+    fn binary_version_is_checked_before_content() {
+        let world = load_baseline_world();
+
+        let mut binary = vec![];
+        save_world(&world, "base", "tests", WorldEncoding::Binary, &mut binary).unwrap();
+
+        let next_version = bincode::serialize(&(WORLD_FORMAT_VERSION + 1)).unwrap();
+        binary[BINARY_MAGIC.len()..BINARY_MAGIC.len() + BINARY_VERSION_SIZE].copy_from_slice(&next_version);
+
+        let (reloaded, _) = ecs::create_default_world();
+        match load_world(&reloaded, binary.as_slice()) {
+            Err(WorldError::UnsupportedVersion(version)) => assert_eq!(version, WORLD_FORMAT_VERSION + 1),
+            _ => panic!("version binaire inconnue acceptée"),
         }
+        assert_eq!(reloaded.entities().join().count(), 0);
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,272 @@
+use crate::{
+    ecs::{
+        self,
+        level::{
+            Level,
+            LevelConfig,
+        },
+        rect::RectComponent,
+        serialization::WorldError,
+    },
+    utils::{
+        input_manager::RefInputManager,
+        math::Rect,
+    },
+};
+use specs::{
+    Join,
+    saveload::{
+        Marker,
+        U64Marker,
+    },
+};
+use std::path::PathBuf;
+
+/// Exécute un niveau sans fenêtre ni GPU, tick par tick, avec les mêmes systèmes que le jeu.
+pub struct Simulation<'a, 'b> {
+    level: Level<'a, 'b>,
+    input_manager: RefInputManager,
+    tick: u64,
+}
+
+impl<'a, 'b> Simulation<'a, 'b> {
+    pub fn load(config: LevelConfig) -> Result<Self, WorldError> {
+        let input_manager = RefInputManager::default();
+
+        let level = Level::load_headless(config, |builder| ecs::build_game_dispatcher(builder, input_manager.clone()))?;
+
+        Ok(Simulation { level, input_manager, tick: 0 })
+    }
+
+    pub fn get_level(&self) -> &Level<'a, 'b> { &self.level }
+    pub fn get_level_mut(&mut self) -> &mut Level<'a, 'b> { &mut self.level }
+
+    /// Gestionnaire d'entrées lu par l'`InputSystem`, permet de simuler des touches entre deux ticks.
+    pub fn get_input_manager(&self) -> RefInputManager { self.input_manager.clone() }
+
+    pub fn get_tick(&self) -> u64 { self.tick }
+
+    pub fn step(&mut self) {
+        self.level.update_headless();
+
+        let mut input_manager = self.input_manager.lock().unwrap();
+        input_manager.record_tick();
+        input_manager.update();
+
+        self.tick += 1;
+    }
+
+    pub fn run(&mut self, ticks: u64) {
+        for _ in 0..ticks {
+            self.step();
+        }
+    }
+
+    /// Rectangles des entités marquées, triés par identifiant de marqueur.
+    pub fn entities_rects(&self) -> Vec<(u64, Rect)> {
+        let world = self.level.get_world();
+        let markers = world.read_storage::<U64Marker>();
+        let rects = world.read_storage::<RectComponent>();
+
+        let mut entities_rects: Vec<(u64, Rect)> = (&markers, &rects).join().map(|(marker, rect)| (marker.id(), rect.get_rect())).collect();
+        entities_rects.sort_by_key(|(id, _)| *id);
+
+        entities_rects
+    }
+}
+
+/// Point d'entrée de `--headless <dossier du niveau> [ticks]`.
+pub fn run_headless(level_dir: PathBuf, ticks: u64) {
+    let config = LevelConfig::load(level_dir).unwrap_or_else(|err| panic!("Impossible de charger la configuration du niveau ! Erreur : {}", err));
+
+    let mut simulation = Simulation::load(config).unwrap_or_else(|err| panic!("Impossible de charger le monde du niveau ! Erreur : {}", err));
+
+    simulation.run(ticks);
+
+    println!("Niveau \"{}\" simulé pendant {} ticks", simulation.get_level().get_config().name, simulation.get_tick());
+
+    for (id, rect) in simulation.entities_rects() {
+        println!("{} : x = {:.2}, y = {:.2}, w = {}, h = {}", id, rect.pos.x, rect.pos.y, rect.size.x, rect.size.y);
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use crate::{
+        ecs::{
+            self,
+            actions::Actions,
+            inputs::InputComponent,
+            level::{
+                Background,
+                FollowEntity,
+                LevelConfig,
+            },
+            physics::{
+                BodyType,
+                PhysicsComponent,
+            },
+            rect::RectComponent,
+            serialization::{
+                self,
+                WorldEncoding,
+            },
+        },
+        utils::{
+            constants,
+            input_manager::InputManager,
+            math::Rect,
+        },
+    };
+    use ggez::event::Keycode;
+    use specs::{
+        Builder,
+        saveload::{
+            MarkedBuilder,
+            U64Marker,
+        },
+        World,
+    };
+    use std::{
+        env,
+        fs::{
+            self,
+            File,
+        },
+        process,
+    };
+    use super::Simulation;
+
+    /// Enregistre un niveau temporaire peuplé par `populate`, le charge dans une simulation puis le supprime.
+    /// Les marqueurs sont alloués dans l'ordre de création, `entities_rects` suit donc cet ordre.
+    pub fn load_test_level<'a, 'b, F: FnOnce(&mut World)>(name: &str, populate: F) -> Simulation<'a, 'b> {
+        let dir = env::temp_dir().join(format!("rusty_platform_{}_{}", name, process::id()));
+        fs::create_dir_all(&dir).unwrap();
+
+        let (mut world, _) = ecs::create_default_world();
+        populate(&mut world);
+
+        let world_file = File::create(dir.join(constants::path::LEVEL_WORLD_DATA_FILE.as_path())).unwrap();
+        serialization::save_world(&world, name, "tests", WorldEncoding::Ron, world_file).unwrap();
+
+        let config = LevelConfig {
+            author: "tests".to_owned(),
+            name: name.to_owned(),
+            background: Background::default(),
+            dir: dir.clone(),
+            initial_follow_entity: FollowEntity::default(),
+            goals: Default::default(),
+            parallax: Default::default(),
+            lighting: Default::default(),
+            music: None,
+            world_encoding: WorldEncoding::Ron,
+        };
+
+        let simulation = Simulation::load(config).unwrap();
+
+        // Le monde est entièrement lu au chargement, le dossier n'est plus utile
+        fs::remove_dir_all(&dir).unwrap();
+
+        simulation
+    }
+
+    pub fn create_player(world: &mut World, rect: Rect) {
+        world.create_entity()
+            .with(RectComponent::new(rect))
+            .with(PhysicsComponent::new(BodyType::Dynamic { apply_gravity: true }))
+            .marked::<U64Marker>()
+            .build();
+    }
+
+    pub fn create_floor(world: &mut World, rect: Rect) {
+        world.create_entity()
+            .with(RectComponent::new(rect))
+            .with(PhysicsComponent::new(BodyType::Static))
+            .marked::<U64Marker>()
+            .build();
+    }
+
+    /// Appuie et relâche des touches à des ticks fixes, comme le ferait un joueur.
+    fn play_inputs(simulation: &mut Simulation) {
+        for tick in 0..300 {
+            {
+                let input_manager = simulation.get_input_manager();
+                let mut input_manager = input_manager.lock().unwrap();
+
+                match tick {
+                    10 => input_manager.update_key(Keycode::Right, true),
+                    60 => input_manager.update_key(Keycode::Up, true),
+                    70 => input_manager.update_key(Keycode::Up, false),
+                    200 => input_manager.update_key(Keycode::Right, false),
+                    _ => {}
+                }
+            }
+
+            simulation.step();
+        }
+    }
+
+    fn create_controlled_level(name: &str) -> Simulation<'static, 'static> {
+        load_test_level(name, |world| {
+            world.create_entity()
+                .with(RectComponent::new(Rect::new(100., 200., 50, 50)))
+                .with(PhysicsComponent::new(BodyType::Dynamic { apply_gravity: true }))
+                .with(InputComponent::new(vec![
+                    (Keycode::Right, true, Actions::PhysicsForce([1., 0.].into())),
+                    (Keycode::Right, false, Actions::PhysicsForce([1., 0.].into())),
+                    (Keycode::Up, true, Actions::PhysicsJump(150)),
+                ]))
+                .marked::<U64Marker>()
+                .build();
+
+            create_floor(world, Rect::new(0., 300., 2000, 50));
+        })
+    }
+
+    #[test]
+    fn replay_is_deterministic() {
+        let mut recorded = create_controlled_level("replay_recorded");
+        recorded.get_input_manager().lock().unwrap().start_recording();
+        play_inputs(&mut recorded);
+
+        let replay = recorded.get_input_manager().lock().unwrap().stop_recording().unwrap();
+        assert_eq!(replay.len(), 300);
+
+        let mut replayed = create_controlled_level("replay_replayed");
+        *replayed.get_input_manager().lock().unwrap() = InputManager::from_replay(replay.clone());
+        replayed.run(replay.len() as u64);
+
+        let recorded_rects = recorded.entities_rects();
+        let replayed_rects = replayed.entities_rects();
+
+        // Le joueur s'est bien déplacé, sinon la comparaison ne prouve rien
+        assert!(recorded_rects[0].1.left() > 100.);
+
+        assert_eq!(recorded_rects.len(), replayed_rects.len());
+        for ((recorded_id, recorded_rect), (replayed_id, replayed_rect)) in recorded_rects.into_iter().zip(replayed_rects.into_iter()) {
+            assert_eq!(recorded_id, replayed_id);
+            assert_eq!(recorded_rect.pos, replayed_rect.pos);
+        }
+    }
+
+    #[test]
+    fn body_falls_onto_floor() {
+        let mut simulation = load_test_level("body_falls_onto_floor", |world| {
+            create_player(world, Rect::new(100., 0., 50, 50));
+            create_floor(world, Rect::new(0., 300., 500, 50));
+        });
+
+        simulation.run(constants::DESIRED_FPS as u64 * 2);
+
+        let rects = simulation.entities_rects();
+        assert_eq!(rects.len(), 2);
+
+        let (_, player) = rects[0];
+        assert!((player.bottom() - 300.).abs() <= constants::PHYSICS_EPSILON, "le joueur doit reposer sur le sol, bas = {}", player.bottom());
+        assert_eq!(player.left(), 100.);
+
+        // Les corps statiques ne bougent pas
+        let (_, floor) = rects[1];
+        assert_eq!((floor.left(), floor.top()), (0., 300.));
+    }
+}
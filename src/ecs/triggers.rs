@@ -0,0 +1,194 @@
+use crate::ecs::{
+    actions::{
+        ActionComponent,
+        Actions,
+    },
+    chunk::{
+        ActiveChunkMarker,
+        ChunksGrid,
+    },
+    inputs::InputComponent,
+    rect::RectComponent,
+};
+use serde::{
+    Deserialize,
+    Serialize,
+};
+use specs::{
+    prelude::*,
+    saveload::{
+        Marker,
+        MarkerAllocator,
+        U64Marker,
+        U64MarkerAllocator,
+    },
+};
+use specs_derive::Component;
+
+/// Entités pouvant activer la zone.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub enum TriggerFilter {
+    Any,
+    /// Entités contrôlées par le joueur
+    WithInput,
+    Marked(Option<U64Marker>),
+}
+
+/// Entité qui reçoit l'action lorsque la zone est activée.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub enum TriggerTarget {
+    Trigger,
+    Other,
+    Marked(Option<U64Marker>),
+}
+
+/// Zone définie par le `RectComponent` de l'entité, qui déclenche des actions lorsqu'une autre entité y entre, y reste ou en sort.
+#[derive(Component, Serialize, Deserialize, Clone, Debug)]
+pub struct TriggerComponent {
+    pub filter: TriggerFilter,
+    pub on_enter: Vec<(TriggerTarget, Actions)>,
+    pub on_stay: Vec<(TriggerTarget, Actions)>,
+    pub on_exit: Vec<(TriggerTarget, Actions)>,
+    #[serde(skip)]
+    inside: Vec<Entity>,
+    #[serde(skip)]
+    entered: Vec<Entity>,
+}
+
+impl Default for TriggerComponent {
+    fn default() -> Self {
+        TriggerComponent {
+            filter: TriggerFilter::WithInput,
+            on_enter: vec![],
+            on_stay: vec![],
+            on_exit: vec![],
+            inside: vec![],
+            entered: vec![],
+        }
+    }
+}
+
+impl TriggerComponent {
+    /// Entités entrées dans la zone pendant le dernier tick.
+    pub fn get_entered(&self) -> &[Entity] { &self.entered }
+}
+
+pub struct TriggerSystem;
+
+impl TriggerSystem {
+    fn fire<'a>(trigger: Entity, other: Entity, trigger_actions: &[(TriggerTarget, Actions)], entities: &Entities<'a>, u64_marker_allocator: &U64MarkerAllocator, actions: &mut WriteStorage<'a, ActionComponent>) {
+        for (target, action) in trigger_actions.iter() {
+            let target = match target {
+                TriggerTarget::Trigger => Some(trigger),
+                TriggerTarget::Other => Some(other),
+                TriggerTarget::Marked(marker) => marker.and_then(|m| u64_marker_allocator.retrieve_entity_internal(m.id())),
+            };
+
+            // Un marqueur peut encore désigner une entité supprimée
+            if let Some(target) = target.filter(|t| entities.is_alive(*t)) {
+                ActionComponent::push_action(actions, target, action.clone());
+            }
+        }
+    }
+}
+
+impl<'a> System<'a> for TriggerSystem {
+    type SystemData = (
+        Entities<'a>,
+        Read<'a, U64MarkerAllocator>,
+        ReadExpect<'a, ChunksGrid>,
+        WriteStorage<'a, TriggerComponent>,
+        WriteStorage<'a, ActionComponent>,
+        ReadStorage<'a, RectComponent>,
+        ReadStorage<'a, InputComponent>,
+        ReadStorage<'a, U64Marker>,
+        ReadStorage<'a, ActiveChunkMarker>,
+    );
+
+    fn run(&mut self, (entities, u64_marker_allocator, chunks_grid, mut triggers, mut actions, rects, inputs, markers, active_chunk): Self::SystemData) {
+        for (trigger_ent, trigger, trigger_rect, _) in (&*entities, &mut triggers, &rects, &active_chunk).join() {
+            let trigger_rect = trigger_rect.get_rect();
+
+            let inside: Vec<Entity> = chunks_grid.get_entities_in_rect(trigger_rect).into_iter()
+                .filter(|e| *e != trigger_ent && entities.is_alive(*e))
+                .filter(|e| rects.get(*e).map_or(false, |r| r.get_rect().overlaps(trigger_rect)))
+                .filter(|e| match trigger.filter {
+                    TriggerFilter::Any => true,
+                    TriggerFilter::WithInput => inputs.contains(*e),
+                    TriggerFilter::Marked(marker) => marker.map_or(false, |m| markers.get(*e).map_or(false, |e_marker| e_marker.id() == m.id())),
+                })
+                .collect();
+
+            trigger.entered.clear();
+
+            // Les entités supprimées depuis le dernier tick sortent de la zone sans déclencher d'action
+            trigger.inside.retain(|e| entities.is_alive(*e));
+
+            for ent in inside.iter() {
+                if trigger.inside.contains(ent) {
+                    Self::fire(trigger_ent, *ent, &trigger.on_stay, &entities, &u64_marker_allocator, &mut actions);
+                } else {
+                    Self::fire(trigger_ent, *ent, &trigger.on_enter, &entities, &u64_marker_allocator, &mut actions);
+                    trigger.entered.push(*ent);
+                }
+            }
+
+            for ent in trigger.inside.iter().filter(|e| !inside.contains(*e)) {
+                Self::fire(trigger_ent, *ent, &trigger.on_exit, &entities, &u64_marker_allocator, &mut actions);
+            }
+
+            trigger.inside = inside;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        ecs::{
+            actions::Actions,
+            rect::RectComponent,
+            simulation::tests::{
+                create_player,
+                load_test_level,
+            },
+        },
+        utils::math::Rect,
+    };
+    use nalgebra::Vector2;
+    use specs::{
+        Builder,
+        saveload::{
+            MarkedBuilder,
+            U64Marker,
+        },
+    };
+    use super::{
+        TriggerComponent,
+        TriggerFilter,
+        TriggerTarget,
+    };
+
+    #[test]
+    fn deleted_entity_leaves_trigger_silently() {
+        let mut simulation = load_test_level("trigger_delete", |world| {
+            create_player(world, Rect::new(100., 100., 50, 50));
+
+            world.create_entity()
+                .with(RectComponent::new(Rect::new(50., 50., 200, 150)))
+                .with(TriggerComponent {
+                    filter: TriggerFilter::Any,
+                    on_enter: vec![(TriggerTarget::Other, Actions::DeleteEntity)],
+                    on_exit: vec![(TriggerTarget::Other, Actions::Move(Vector2::new(10., 0.)))],
+                    ..TriggerComponent::default()
+                })
+                .marked::<U64Marker>()
+                .build();
+        });
+
+        // L'action de sortie visait l'entité supprimée et paniquait au tick suivant
+        simulation.run(10);
+
+        assert_eq!(simulation.entities_rects().len(), 1);
+    }
+}
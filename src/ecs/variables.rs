@@ -0,0 +1,61 @@
+use serde::{
+    Deserialize,
+    Serialize,
+};
+use specs::prelude::*;
+use specs_derive::Component;
+use std::collections::BTreeMap;
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub enum VariableValue {
+    Int(i64),
+    Float(f64),
+    Bool(bool),
+    Str(String),
+}
+
+impl VariableValue {
+    /// Valeur numérique de la variable, `None` pour les chaînes.
+    pub fn as_f64(&self) -> Option<f64> {
+        match self {
+            VariableValue::Int(i) => Some(*i as f64),
+            VariableValue::Float(f) => Some(*f),
+            VariableValue::Bool(b) => Some(if *b { 1. } else { 0. }),
+            VariableValue::Str(_) => None,
+        }
+    }
+}
+
+/// Variables nommées de l'entité, modifiées et testées par les actions (interrupteurs, compteurs..).
+#[derive(Component, Serialize, Deserialize, Clone, Debug, Default)]
+pub struct VariablesComponent {
+    pub vars: BTreeMap<String, VariableValue>,
+}
+
+impl VariablesComponent {
+    pub fn get(&self, name: &str) -> Option<&VariableValue> { self.vars.get(name) }
+
+    pub fn set(&mut self, name: &str, value: VariableValue) {
+        self.vars.insert(name.to_owned(), value);
+    }
+
+    /// Ajoute `amount` à une variable numérique, une variable absente est créée à `amount`.
+    pub fn increment(&mut self, name: &str, amount: i64) {
+        let value = self.vars.entry(name.to_owned()).or_insert(VariableValue::Int(0));
+
+        match value {
+            VariableValue::Int(i) => *i = i.saturating_add(amount),
+            VariableValue::Float(f) => *f += amount as f64,
+            _ => {}
+        }
+    }
+
+    /// Inverse une variable booléenne, une variable absente est créée à `true`.
+    pub fn toggle(&mut self, name: &str) {
+        let value = self.vars.entry(name.to_owned()).or_insert(VariableValue::Bool(false));
+
+        if let VariableValue::Bool(b) = value {
+            *b = !*b;
+        }
+    }
+}
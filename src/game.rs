@@ -6,6 +6,10 @@ use crate::{
         SceneState,
     },
     utils::{
+        audio::{
+            self,
+            VolumeConfig,
+        },
         constants,
         input_manager::RefInputManager,
     },
@@ -47,20 +51,22 @@ use std::{
 
 
 #[derive(Serialize, Deserialize, Debug)]
-struct GameConfig {
+pub struct GameConfig {
     window_size: (u32, u32),
     fullscreen_type: FullscreenType,
     borderless: bool,
     vsync: bool,
+    #[serde(default)]
+    pub volumes: VolumeConfig,
 }
 
 impl GameConfig {
-    fn load() -> Result<Self, ron::de::Error> {
+    pub fn load() -> Result<Self, ron::de::Error> {
         let config_file = File::open(constants::path::GAME_CONFIG_FILE.as_path()).map_err(|err| ron::de::Error::from(err))?;
         ron::de::from_reader::<File, Self>(config_file)
     }
 
-    fn save(&self) {
+    pub fn save(&self) {
         let mut config_file = File::create(constants::path::GAME_CONFIG_FILE.as_path()).expect("Impossible de créer le fichier de configuration !");
         let content = ron::ser::to_string_pretty(&self, Default::default()).expect("Impossible de sérialiser la configuration !");
         config_file.write_all(content.as_bytes()).expect("Impossible d'écrire la configuration dans le fichier !");
@@ -69,7 +75,7 @@ impl GameConfig {
 
 impl Default for GameConfig {
     fn default() -> Self {
-        GameConfig { window_size: (1280, 720), fullscreen_type: FullscreenType::Off, borderless: false, vsync: true }
+        GameConfig { window_size: (1280, 720), fullscreen_type: FullscreenType::Off, borderless: false, vsync: true, volumes: VolumeConfig::default() }
     }
 }
 
@@ -83,13 +89,15 @@ pub struct Game {
 
 impl Game {
     pub fn new() -> Self {
-        let GameConfig { window_size: (width, height), fullscreen_type, borderless, vsync } = GameConfig::load().unwrap_or_else(|err| {
+        let GameConfig { window_size: (width, height), fullscreen_type, borderless, vsync, volumes } = GameConfig::load().unwrap_or_else(|err| {
             eprintln!("Le fichier de configuration est inexistant ou corrompu ! Création de la configuration par défaut.. Erreur : {}", err);
             let config = GameConfig::default();
             config.save();
             config
         });
 
+        audio::set_volumes(volumes);
+
         let window_setup = WindowSetup {
             title: "Rusty-Platform".to_string(),
             icon: "/icon.png".to_string(),
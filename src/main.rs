@@ -1,4 +1,16 @@
-use crate::game::Game;
+use crate::{
+    ecs::{
+        level::LevelConfig,
+        serialization::WorldEncoding,
+        simulation,
+    },
+    game::Game,
+    utils::constants,
+};
+use std::{
+    env,
+    path::PathBuf,
+};
 
 mod wrapper;
 mod ecs;
@@ -7,5 +19,26 @@ mod utils;
 mod game;
 
 pub fn main() {
-    Game::new().run();
-}
\ No newline at end of file
+    let args: Vec<String> = env::args().collect();
+
+    match args.get(1).map(|arg| arg.as_str()) {
+        Some("--headless") => {
+            let level_dir = PathBuf::from(args.get(2).expect("Utilisation : --headless <dossier du niveau> [ticks]"));
+            let ticks = args.get(3).map_or(constants::DESIRED_FPS as u64 * 10, |ticks| ticks.parse().expect("Nombre de ticks invalide !"));
+
+            simulation::run_headless(level_dir, ticks);
+        }
+        Some("--convert-world") => {
+            let usage = "Utilisation : --convert-world <dossier du niveau> <ron|binaire>";
+            let level_dir = PathBuf::from(args.get(2).expect(usage));
+            let encoding_name = args.get(3).expect(usage);
+            let encoding = encoding_name.parse::<WorldEncoding>().unwrap_or_else(|err| panic!("Encodage invalide ! Erreur : {}", err));
+
+            let mut config = LevelConfig::load(level_dir).unwrap_or_else(|err| panic!("Impossible de charger la configuration du niveau ! Erreur : {}", err));
+            config.convert_world(encoding).unwrap_or_else(|err| panic!("Impossible de convertir le monde du niveau ! Erreur : {}", err));
+
+            println!("Monde du niveau \"{}\" converti en {}", config.name, encoding_name);
+        }
+        _ => Game::new().run()
+    }
+}
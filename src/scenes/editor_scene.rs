@@ -2,14 +2,38 @@ use crate::{
     ecs::{
         self,
         actions::Actions,
+        ai::AiComponent,
         actions::ActionSystem,
+        animation::{
+            AnimationComponent,
+            AnimationSystem,
+        },
+        audio::MusicConfig,
+        checkpoints::CheckpointComponent,
         chunk::ActiveChunkMarker,
+        collectibles::{
+            CollectibleComponent,
+            DoorComponent,
+            InventoryComponent,
+        },
+        health::{
+            DamageComponent,
+            HealthComponent,
+        },
         imgui_editor::ImGuiEditor,
         inputs::{
             InputComponent,
             InputSystem,
         },
+        lighting::LightComponent,
+        loading,
+        particles::{
+            ParticleEmitterComponent,
+            ParticleSystem,
+        },
+        path::PathComponent,
         level::{
+            Background,
             Level,
             LevelConfig,
         },
@@ -24,6 +48,14 @@ use crate::{
             SpriteImage,
             SpriteMode,
         },
+        scripting::ScriptComponent,
+        serialization::{
+            self,
+            WorldEncoding,
+            WorldError,
+        },
+        triggers::TriggerComponent,
+        variables::VariablesComponent,
     },
     scenes::{
         editor_try_level_scene::EditorTryLevelScene,
@@ -62,6 +94,7 @@ use imgui::{
     EditableColor,
     im_str,
     ImStr,
+    ImString,
     Ui,
 };
 use imgui_sys;
@@ -81,7 +114,10 @@ use specs::{
 };
 use std::{
     collections::HashMap,
-    path::Path,
+    path::{
+        Path,
+        PathBuf,
+    },
 };
 use std::fmt::Debug;
 use std::fmt::Error;
@@ -95,6 +131,20 @@ lazy_static! {
         wrappers.insert(ComponentsWrapper::Input, im_str!("Entrée"));
         wrappers.insert(ComponentsWrapper::Physics, im_str!("Physique"));
         wrappers.insert(ComponentsWrapper::Sprite, im_str!("Sprite"));
+        wrappers.insert(ComponentsWrapper::Path, im_str!("Chemin"));
+        wrappers.insert(ComponentsWrapper::Trigger, im_str!("Zone de déclenchement"));
+        wrappers.insert(ComponentsWrapper::Checkpoint, im_str!("Point de contrôle"));
+        wrappers.insert(ComponentsWrapper::Health, im_str!("Vie"));
+        wrappers.insert(ComponentsWrapper::Damage, im_str!("Dégâts"));
+        wrappers.insert(ComponentsWrapper::Collectible, im_str!("Objet à ramasser"));
+        wrappers.insert(ComponentsWrapper::Inventory, im_str!("Inventaire"));
+        wrappers.insert(ComponentsWrapper::Door, im_str!("Porte"));
+        wrappers.insert(ComponentsWrapper::Ai, im_str!("IA"));
+        wrappers.insert(ComponentsWrapper::Variables, im_str!("Variables"));
+        wrappers.insert(ComponentsWrapper::Script, im_str!("Script"));
+        wrappers.insert(ComponentsWrapper::Animation, im_str!("Animation"));
+        wrappers.insert(ComponentsWrapper::Light, im_str!("Lumière"));
+        wrappers.insert(ComponentsWrapper::ParticleEmitter, im_str!("Particules"));
         wrappers
     };
 }
@@ -105,6 +155,20 @@ enum ComponentsWrapper {
     Input,
     Physics,
     Sprite,
+    Path,
+    Trigger,
+    Checkpoint,
+    Health,
+    Damage,
+    Collectible,
+    Inventory,
+    Door,
+    Ai,
+    Variables,
+    Script,
+    Animation,
+    Light,
+    ParticleEmitter,
 }
 
 macro_rules! impl_components_wrapper {
@@ -149,7 +213,21 @@ impl_components_wrapper!([
     ComponentsWrapper::Rect => RectComponent,
     ComponentsWrapper::Input => InputComponent,
     ComponentsWrapper::Physics => PhysicsComponent,
-    ComponentsWrapper::Sprite => SpriteComponent
+    ComponentsWrapper::Sprite => SpriteComponent,
+    ComponentsWrapper::Path => PathComponent,
+    ComponentsWrapper::Trigger => TriggerComponent,
+    ComponentsWrapper::Checkpoint => CheckpointComponent,
+    ComponentsWrapper::Health => HealthComponent,
+    ComponentsWrapper::Damage => DamageComponent,
+    ComponentsWrapper::Collectible => CollectibleComponent,
+    ComponentsWrapper::Inventory => InventoryComponent,
+    ComponentsWrapper::Door => DoorComponent,
+    ComponentsWrapper::Ai => AiComponent,
+    ComponentsWrapper::Variables => VariablesComponent,
+    ComponentsWrapper::Script => ScriptComponent,
+    ComponentsWrapper::Animation => AnimationComponent,
+    ComponentsWrapper::Light => LightComponent,
+    ComponentsWrapper::ParticleEmitter => ParticleEmitterComponent
 ]);
 
 struct ImGuiMemoryHelper {
@@ -191,6 +269,7 @@ enum EditorMode<'a, 'b> {
     Select(Entity, Option<Vec<Entity>>),
     Copy(Entity, Option<Vec<Entity>>),
     SelectEntityCallback(EntityCallback<'a, 'b>),
+    EditPath(Entity),
 }
 
 pub struct EditorScene<'a, 'b> {
@@ -203,23 +282,31 @@ pub struct EditorScene<'a, 'b> {
 }
 
 impl<'a, 'b> EditorScene<'a, 'b> {
-    pub fn load_level(ctx: &mut Context, resources_manager: Option<ResourcesManager>, input_manager: RefInputManager, config: LevelConfig) -> Self {
-        let level = Level::load(ctx, config, resources_manager, |builder| {
+    pub fn load_level(ctx: &mut Context, resources_manager: Option<ResourcesManager>, input_manager: RefInputManager, config: LevelConfig) -> Result<Self, WorldError> {
+        let mut level = Level::load(ctx, config, resources_manager, |builder| {
             builder
                 .with(InputSystem { input_manager: input_manager.clone() }, "input_manager", &[])
                 .with(ActionSystem, "action_system", &["input_manager"])
-        });
+                .with(AnimationSystem, "anim_sys", &[])
+                .with(ParticleSystem::default(), "particle_sys", &["action_system"])
+        })?;
+
+        level.set_parallax_enabled(false);
+        // La musique est lancée depuis le menu du niveau
+        level.stop_music();
 
         let camera = Camera::new(ctx.screen_size(),Vector2::new(constants::CAMERA_VIEW_SIZE.0, constants::CAMERA_VIEW_SIZE.1), 1.);
 
-        EditorScene { level, input_manager, camera, mode: EditorMode::Default, imgui_helper: ImGuiMemoryHelper::new(), is_ui_hover: false }
+        Ok(EditorScene { level, input_manager, camera, mode: EditorMode::Default, imgui_helper: ImGuiMemoryHelper::new(), is_ui_hover: false })
     }
 
     pub fn new_level(ctx: &mut Context, input_manager: RefInputManager, name: String) -> Self {
-        let level = Level::new(ctx, String::from("finch"), name, |builder| {
+        let mut level = Level::new(ctx, String::from("finch"), name, |builder| {
             builder
                 .with(InputSystem { input_manager: input_manager.clone() }, "input_manager", &[])
                 .with(ActionSystem, "action_system", &["input_manager"])
+                .with(AnimationSystem, "anim_sys", &[])
+                .with(ParticleSystem::default(), "particle_sys", &["action_system"])
         }, |world| {
             for x in 0..1 {
                 for y in 0..1 {
@@ -230,6 +317,8 @@ impl<'a, 'b> EditorScene<'a, 'b> {
             //Self::create_entity(world, Point2::new(0., 200.), Vector2::new(100, 100), SpriteMode::Stretch, true);
         });
 
+        level.set_parallax_enabled(false);
+
         let camera = Camera::new(ctx.screen_size(), Vector2::new(constants::CAMERA_VIEW_SIZE.0, constants::CAMERA_VIEW_SIZE.1), 1.);
 
         EditorScene { level, input_manager, camera, mode: EditorMode::Default, imgui_helper: ImGuiMemoryHelper::new(), is_ui_hover: false }
@@ -249,11 +338,19 @@ impl<'a, 'b> EditorScene<'a, 'b> {
             ]));
         }
 
-        builder.with(PhysicsComponent::new(BodyType::Dynamic { apply_gravity: true, jump_data: None }))
+        builder.with(PhysicsComponent::new(BodyType::Dynamic { apply_gravity: true }))
             .marked::<U64Marker>()
             .build();
     }
 
+    fn create_checkpoint(world: &mut World, pos: Point2<f64>) -> Entity {
+        world.create_entity()
+            .with(RectComponent::new((pos, Vector2::new(50, 100)).into()))
+            .with(CheckpointComponent::default())
+            .marked::<U64Marker>()
+            .build()
+    }
+
     pub fn clone_entity(world: &mut World, ent: Entity, new_pos: Point2<f64>) -> Entity {
         let copy_ent = ecs::copy_entity(ent, world);
 
@@ -305,7 +402,7 @@ impl<'a, 'b> EditorScene<'a, 'b> {
         if !self.is_ui_hover {
             let input_manager = self.input_manager.lock().unwrap();
 
-            let chunks_bounds = Some(self.level.get_chunk_sys().get_bounds_chunks());
+            let chunks_bounds = Some(self.level.get_chunks_bounds());
 
             let mut mv = Vector2::new(0., 0.);
 
@@ -390,7 +487,7 @@ impl<'a, 'b> Scene for EditorScene<'a, 'b> {
                                         let mut move_x = delta_mouse_in_world.x + camera_move_in_world.x;
                                         let mut move_y = delta_mouse_in_world.y + camera_move_in_world.y;
 
-                                        let bounds = self.level.get_chunk_sys().get_bounds_chunks();
+                                        let bounds = self.level.get_chunks_bounds();
 
                                         if !(bounds.left() <= rect.pos().x + move_x && bounds.right() >= rect.pos().x + move_x + rect.size().x as f64) {
                                             move_x = 0.;
@@ -473,6 +570,17 @@ impl<'a, 'b> Scene for EditorScene<'a, 'b> {
 
                         Some(EditorMode::Default)
                     }
+                    EditorMode::EditPath(entity) => {
+                        if jp && !self.is_ui_hover {
+                            let size = self.level.get_world().read_storage::<RectComponent>().get(entity).map_or(Vector2::new(0, 0), |rect| rect.size());
+
+                            if let Some(path) = self.level.get_world().write_storage::<PathComponent>().get_mut(entity) {
+                                path.waypoints.push(Point2::new(mouse_in_world.x - size.x as f64 / 2., mouse_in_world.y - size.y as f64 / 2.));
+                            }
+                        }
+
+                        None
+                    }
                 };
 
                 if let Some(mode) = next_mode {
@@ -512,6 +620,9 @@ impl<'a, 'b> Scene for EditorScene<'a, 'b> {
                     }
                     EditorMode::SelectionRectangle(_, _) => {}
                     EditorMode::SelectEntityCallback(_) => {}
+                    EditorMode::EditPath(entity) => {
+                        self.mode = EditorMode::Select(entity, None);
+                    }
                 }
             }
 
@@ -606,7 +717,7 @@ impl<'a, 'b> Scene for EditorScene<'a, 'b> {
 
                     if let Some(spr) = sprite_storage.get_mut(entity) {
                         if let Some(ref image) = spr.image {
-                            render::draw_sprite(ctx, &self.camera, image, select_rect_placed, &spr.mode);
+                            render::draw_sprite(ctx, &self.camera, image, graphics::Rect::one(), select_rect_placed, &spr.mode);
                         }
                     }
 
@@ -620,7 +731,7 @@ impl<'a, 'b> Scene for EditorScene<'a, 'b> {
 
                                         let rect = Rect::from(Point2::new(select_rect_placed.pos.x - delta_x, select_rect_placed.pos.y - delta_y), other_rect.get_rect().size);
 
-                                        render::draw_sprite(ctx, &self.camera, image, rect, &spr.mode);
+                                        render::draw_sprite(ctx, &self.camera, image, graphics::Rect::one(), rect, &spr.mode);
                                     }
                                 }
                             }
@@ -631,8 +742,43 @@ impl<'a, 'b> Scene for EditorScene<'a, 'b> {
                 graphics::set_color(ctx, default_color)?;
             }
             EditorMode::SelectEntityCallback(cb) => {}
+            EditorMode::EditPath(entity) => {
+                let rect_storage = self.level.get_world().read_storage::<RectComponent>();
+
+                if let (Some(rect), Some(path)) = (rect_storage.get(entity), self.level.get_world().read_storage::<PathComponent>().get(entity)) {
+                    let old_color = graphics::get_color(ctx);
+                    graphics::set_color(ctx, (40, 180, 255).into())?;
+
+                    let mut previous = self.camera.world_point_to_screen(rect.pos());
+
+                    for waypoint in path.waypoints.iter() {
+                        let waypoint_rect = self.camera.world_rect_to_screen(Rect::from(*waypoint, rect.size()));
+
+                        graphics::rectangle(ctx, graphics::DrawMode::Line(2.0), waypoint_rect.to_ggez_rect())?;
+                        graphics::line(ctx, &[graphics::Point2::new(previous.x as f32, previous.y as f32), graphics::Point2::new(waypoint_rect.pos.x as f32, waypoint_rect.pos.y as f32)], 2.)?;
+
+                        previous = waypoint_rect.pos;
+                    }
+
+                    graphics::set_color(ctx, old_color)?;
+                }
+            }
         }
 
+        {
+            let old_color = graphics::get_color(ctx);
+            graphics::set_color(ctx, (255, 200, 0).into())?;
+
+            let world = self.level.get_world();
+
+            for (rect, checkpoint) in (&world.read_storage::<RectComponent>(), &world.read_storage::<CheckpointComponent>()).join() {
+                let spawn = self.camera.world_point_to_screen(rect.pos() + checkpoint.spawn_offset);
+
+                graphics::circle(ctx, graphics::DrawMode::Fill, graphics::Point2::new(spawn.x as f32, spawn.y as f32), 5., 1.)?;
+            }
+
+            graphics::set_color(ctx, old_color)?;
+        }
 
         Ok(NextState::Continue)
     }
@@ -643,8 +789,11 @@ impl<'a, 'b> Scene for EditorScene<'a, 'b> {
         ui.main_menu_bar(|| {
             ui.menu(im_str!("Fichier")).build(|| {
                 if ui.menu_item(im_str!("Sauvegarder et quitter")).build() {
-                    self.level.save();
-                    next_state = NextState::Replace(Box::new(MainScene::new(ctx, self.input_manager.clone())));
+                    // L'éditeur reste ouvert si l'enregistrement échoue, pour ne pas perdre les modifications
+                    match self.level.save() {
+                        Ok(()) => next_state = NextState::Replace(Box::new(MainScene::new(ctx, self.input_manager.clone()))),
+                        Err(err) => eprintln!("Impossible de sauvegarder le niveau {} ! Erreur : {}", self.level.get_config().name, err)
+                    }
                 }
 
                 if ui.menu_item(im_str!("Ajouter une ressources ..")).build() {
@@ -667,7 +816,43 @@ impl<'a, 'b> Scene for EditorScene<'a, 'b> {
                         editor.level.set_follow_camera(ent);
                     }));
                 }
+                if ui.button(im_str!("Ajouter un point de contrôle"), (0., 0.)) {
+                    let view = self.camera.world_view();
+                    let pos = Point2::new(view.pos.x + view.size.x as f64 / 2., view.pos.y + view.size.y as f64 / 2.);
+
+                    let checkpoint = Self::create_checkpoint(self.level.get_world_mut(), pos);
+
+                    self.mode = EditorMode::Select(checkpoint, None);
+                    self.imgui_helper.select_entity_view_show_window = true;
+                    self.imgui_helper.select_entity_view_component_selected = ComponentsWrapper::Checkpoint;
+                }
                 if ui.collapsing_header(im_str!("Arrière plan")).build() {
+                    let color = *self.level.background_color();
+                    let background = self.level.background_mut();
+
+                    let mut background_changed = false;
+
+                    let mut kind = if let Background::Texture(..) = *background { 1 } else { 0 };
+                    if ui.combo(im_str!("type"), &mut kind, &[im_str!("Couleur"), im_str!("Texture")], 2) {
+                        *background = if kind == 1 { Background::Texture(SpriteImage::new_unloaded(PathBuf::new()), color) } else { Background::Color(color) };
+                        background_changed = true;
+                    }
+
+                    if let Background::Texture(ref mut spr_image, _) = *background {
+                        let mut path = ImString::with_capacity(64);
+                        path.push_str(&spr_image.get_path().to_string_lossy());
+
+                        if ui.input_text(im_str!("texture"), &mut path).build() {
+                            let path: &str = path.as_ref();
+                            *spr_image = SpriteImage::new_unloaded(PathBuf::from(path));
+                            background_changed = true;
+                        }
+                    }
+
+                    if background_changed {
+                        self.level.reload_resources();
+                    }
+
                     let color = self.level.background_color_mut();
 
                     let mut editable_color = [color.r, color.g, color.b];
@@ -677,6 +862,88 @@ impl<'a, 'b> Scene for EditorScene<'a, 'b> {
                         color.b = editable_color[2];
                     }
                 }
+                if ui.collapsing_header(im_str!("Éclairage")).build() {
+                    let mut lighting_enabled = self.level.is_lighting_enabled();
+                    if ui.checkbox(im_str!("Activer l'éclairage"), &mut lighting_enabled) {
+                        self.level.set_lighting_enabled(lighting_enabled);
+                    }
+
+                    let ambient = self.level.ambient_light_mut();
+
+                    let mut editable_color = [ambient.r, ambient.g, ambient.b];
+                    if ui.color_edit(im_str!("lumière ambiante"), EditableColor::Float3(&mut editable_color)).build() {
+                        ambient.r = editable_color[0];
+                        ambient.g = editable_color[1];
+                        ambient.b = editable_color[2];
+                    }
+                }
+                if ui.collapsing_header(im_str!("Calques")).build() {
+                    let mut parallax_enabled = self.level.is_parallax_enabled();
+                    if ui.checkbox(im_str!("Aperçu de la parallaxe"), &mut parallax_enabled) {
+                        self.level.set_parallax_enabled(parallax_enabled);
+                    }
+
+                    let parallax = self.level.parallax_mut();
+
+                    let mut factors = [parallax.background as f32, parallax.foreground as f32, parallax.texture as f32];
+                    if ui.drag_float(im_str!("arrière-plan"), &mut factors[0]).speed(0.01).build() {
+                        parallax.background = factors[0] as f64;
+                    }
+                    if ui.drag_float(im_str!("premier plan"), &mut factors[1]).speed(0.01).build() {
+                        parallax.foreground = factors[1] as f64;
+                    }
+                    if ui.drag_float(im_str!("texture d'arrière-plan"), &mut factors[2]).speed(0.01).build() {
+                        parallax.texture = factors[2] as f64;
+                    }
+                }
+                if ui.collapsing_header(im_str!("Musique")).build() {
+                    let music = self.level.music_mut();
+
+                    let mut enabled = music.is_some();
+                    if ui.checkbox(im_str!("Musique de fond"), &mut enabled) {
+                        *music = if enabled { Some(MusicConfig::default()) } else { None };
+                    }
+
+                    if let Some(ref mut music) = *music {
+                        let mut path = ImString::with_capacity(64);
+                        path.push_str(&music.path.to_string_lossy());
+
+                        if ui.input_text(im_str!("fichier"), &mut path).build() {
+                            let path: &str = path.as_ref();
+                            music.path = PathBuf::from(path);
+                        }
+
+                        ui.checkbox(im_str!("en boucle"), &mut music.looping);
+                        ui.slider_float(im_str!("volume"), &mut music.volume, 0., 1.).build();
+                    }
+
+                    if ui.button(im_str!("Écouter"), (100., 0.)) {
+                        self.level.play_music();
+                    }
+                    ui.same_line(0.);
+                    if ui.button(im_str!("Arrêter"), (100., 0.)) {
+                        self.level.stop_music();
+                    }
+                }
+                if ui.collapsing_header(im_str!("Fichier du monde")).build() {
+                    let version = self.level.get_world_version();
+                    ui.text(&format!("Format : {}", version));
+                    if version < serialization::WORLD_FORMAT_VERSION {
+                        ui.text(&format!("Converti au format {} au prochain enregistrement", serialization::WORLD_FORMAT_VERSION));
+                    }
+
+                    let metadata = self.level.get_world_metadata();
+                    ui.text(&format!("{} par {}", metadata.name, metadata.author));
+                    ui.text(&format!("Version du jeu : {}", metadata.game_version));
+                    ui.text(&format!("Entités : {}", metadata.entities));
+
+                    let encoding = self.level.world_encoding_mut();
+
+                    let mut binary = if *encoding == WorldEncoding::Binary { 1 } else { 0 };
+                    if ui.combo(im_str!("encodage"), &mut binary, &[im_str!("RON"), im_str!("Binaire")], 2) {
+                        *encoding = if binary == 1 { WorldEncoding::Binary } else { WorldEncoding::Ron };
+                    }
+                }
             });
 
             ui.text(&format!("{:?}", self.mode));
@@ -705,8 +972,20 @@ impl<'a, 'b> Scene for EditorScene<'a, 'b> {
                         }
                     }
 
+                    let resource_paths = loading::entity_resource_paths(self.level.get_world(), entity);
+
                     self.imgui_helper.select_entity_view_component_selected.draw_ui(entity, self.level.get_world_mut(), ui);
 
+                    if loading::entity_resource_paths(self.level.get_world(), entity) != resource_paths {
+                        self.level.reload_resources();
+                    }
+
+                    if self.imgui_helper.select_entity_view_component_selected == ComponentsWrapper::Path && ComponentsWrapper::Path.has_comp(entity, self.level.get_world()) {
+                        if ui.button(im_str!("Placer les points du chemin"), (-1., 0.)) {
+                            self.mode = EditorMode::EditPath(entity);
+                        }
+                    }
+
                     if ui.button(im_str!("Ajouter un composant"), (-1., 0.)) {
                         ui.open_popup(im_str!("add_comp"));
                     }
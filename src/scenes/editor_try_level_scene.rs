@@ -1,9 +1,8 @@
 use crate::{
     ecs::{
-        actions::ActionSystem,
-        inputs::InputSystem,
+        self,
         level::Level,
-        physics::PhysicsSystem,
+        scripting::Scripting,
     },
     scenes::{
         NextState,
@@ -21,7 +20,11 @@ use ggez::{
     event::Keycode,
     graphics::Color,
 };
-use imgui::Ui;
+use imgui::{
+    im_str,
+    ImGuiCond,
+    Ui,
+};
 use nalgebra::{Vector2, Vector3};
 use crate::utils::ggez::CtxExtension;
 
@@ -33,11 +36,9 @@ pub struct EditorTryLevelScene<'a, 'b> {
 
 impl<'a, 'b> EditorTryLevelScene<'a, 'b> {
     pub fn new(screen_size: Vector2<u32>, input_manager: RefInputManager, editor_level: &Level) -> Self {
-        let level = crate::ecs::level::clone(editor_level, |builder| {
-            builder.with(InputSystem { input_manager: input_manager.clone() }, "input_manager", &[])
-                .with(ActionSystem, "action_system", &["input_manager"])
-                .with(PhysicsSystem { gravity: Vector2::new(0., 9.81) }, "phys_sys", &["action_system"])
-        });
+        let level = crate::ecs::level::clone(editor_level, |builder| ecs::build_game_dispatcher(builder, input_manager.clone()));
+
+        level.get_world().write_resource::<Scripting>().hot_reload = true;
 
         EditorTryLevelScene {
             level,
@@ -80,6 +81,20 @@ impl<'a, 'b> Scene for EditorTryLevelScene<'a, 'b> {
     fn draw_ui(&mut self, ctx: &mut Context, ui: &Ui) -> SceneState {
         let mut next_state = NextState::Continue;
 
+        let mut scripting = self.level.get_world().write_resource::<Scripting>();
+
+        if !scripting.console.is_empty() {
+            ui.window(im_str!("Console")).size((500., 200.), ImGuiCond::FirstUseEver).position((10., 10.), ImGuiCond::FirstUseEver).build(|| {
+                if ui.small_button(im_str!("Effacer")) {
+                    scripting.console.clear();
+                }
+
+                for line in scripting.console.iter() {
+                    ui.text_wrapped(im_str!("{}", line));
+                }
+            });
+        }
+
         Ok(next_state)
     }
 
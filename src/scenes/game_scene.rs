@@ -1,10 +1,10 @@
 use crate::{
     ecs::{
-        actions::ActionSystem,
-        inputs::InputSystem,
+        self,
+        goals::LevelStatus,
         level::Level,
         level::LevelConfig,
-        physics::PhysicsSystem,
+        serialization::WorldError,
     },
     scenes::{
         main_scene::MainScene,
@@ -15,7 +15,11 @@ use crate::{
     utils::{
         camera::Camera,
         constants,
-        input_manager::RefInputManager,
+        input_manager::{
+            InputManager,
+            RefInputManager,
+        },
+        replay::Replay,
     },
     wrapper::imgui_wrapper::CenteredWindow,
 };
@@ -31,32 +35,109 @@ use imgui::{
 };
 use nalgebra::Vector2;
 use crate::utils::ggez::CtxExtension;
+use std::sync::{
+    Arc,
+    Mutex,
+};
 
 pub struct GameScene<'a, 'b> {
     level: Level<'a, 'b>,
     input_manager: RefInputManager,
+    level_input_manager: RefInputManager,
+    replay: Option<Replay>,
     camera: Camera,
     show_exit_menu: bool,
 }
 
 impl<'a, 'b> GameScene<'a, 'b> {
-    pub fn new(ctx: &mut Context, input_manager: RefInputManager, level_config: LevelConfig) -> Self {
-        let level = Level::load(ctx, level_config, None, |builder| {
-            builder.with(InputSystem { input_manager: input_manager.clone() }, "input_manager", &[])
-                .with(ActionSystem, "action_system", &["input_manager"])
-                .with(PhysicsSystem { gravity: Vector2::new(0., 9.81) }, "phys_sys", &["action_system"])
-        });
+    pub fn new(ctx: &mut Context, input_manager: RefInputManager, level_config: LevelConfig) -> Result<Self, WorldError> {
+        let scene = Self::build(ctx, input_manager.clone(), input_manager.clone(), None, level_config)?;
 
-        let camera = Camera::new(ctx.screen_size(),  Vector2::new(constants::CAMERA_VIEW_SIZE.0, constants::CAMERA_VIEW_SIZE.1), 1.);
+        input_manager.lock().unwrap().start_recording();
 
-        GameScene { level, input_manager, camera, show_exit_menu: false }
+        Ok(scene)
+    }
+
+    pub fn new_replay(ctx: &mut Context, input_manager: RefInputManager, level_config: LevelConfig, replay: Replay) -> Result<Self, WorldError> {
+        let replay_input_manager: RefInputManager = Arc::new(Mutex::new(InputManager::from_replay(replay.clone())));
+
+        Self::build(ctx, input_manager, replay_input_manager, Some(replay), level_config)
+    }
+
+    fn build(ctx: &mut Context, input_manager: RefInputManager, level_input_manager: RefInputManager, replay: Option<Replay>, level_config: LevelConfig) -> Result<Self, WorldError> {
+        let level = Self::load_level(ctx, level_config, level_input_manager.clone())?;
+
+        Ok(GameScene { level, input_manager, level_input_manager, replay, camera: Self::create_camera(ctx), show_exit_menu: false })
+    }
+
+    fn load_level(ctx: &mut Context, level_config: LevelConfig, level_input_manager: RefInputManager) -> Result<Level<'a, 'b>, WorldError> {
+        Level::load(ctx, level_config, None, |builder| ecs::build_game_dispatcher(builder, level_input_manager.clone()))
+    }
+
+    fn create_camera(ctx: &mut Context) -> Camera {
+        Camera::new(ctx.screen_size(),  Vector2::new(constants::CAMERA_VIEW_SIZE.0, constants::CAMERA_VIEW_SIZE.1), 1.)
+    }
+
+    /// Recharge le niveau depuis sa configuration et `world.dat`, le niveau actuel est conservé si le chargement échoue.
+    fn restart(&mut self, ctx: &mut Context) {
+        match Self::load_level(ctx, self.level.get_config().clone(), self.level_input_manager.clone()) {
+            Ok(level) => self.level = level,
+            Err(err) => {
+                eprintln!("Impossible de recharger le niveau {} ! Erreur : {}", self.level.get_config().name, err);
+                return;
+            }
+        }
+
+        match self.replay {
+            Some(ref replay) => *self.level_input_manager.lock().unwrap() = InputManager::from_replay(replay.clone()),
+            None => self.input_manager.lock().unwrap().start_recording()
+        }
+
+        self.camera = Self::create_camera(ctx);
+        self.show_exit_menu = false;
+    }
+
+    fn save_replay(&self) {
+        if let Some(replay) = self.input_manager.lock().unwrap().stop_recording() {
+            replay.save(&self.level.get_config().replay_path());
+        }
+    }
+
+    /// Enregistre le replay sans arrêter l'enregistrement, le joueur peut encore reprendre au point de contrôle.
+    fn save_replay_snapshot(&self) {
+        if let Some(replay) = self.input_manager.lock().unwrap().get_recording() {
+            replay.save(&self.level.get_config().replay_path());
+        }
     }
 }
 
 impl<'a, 'b> Scene for GameScene<'a, 'b> {
     fn update(&mut self, ctx: &mut Context, dt: f32) -> SceneState {
-        self.level.update(ctx, &self.camera, dt);
-        self.level.update_follow_camera(&mut self.camera);
+        if self.replay.is_some() && self.level.get_progress().status == LevelStatus::Lost && self.level_input_manager.lock().unwrap().is_replay_respawn() {
+            self.level.respawn();
+        }
+
+        if self.level.get_progress().status == LevelStatus::Playing {
+            self.level.update(ctx, &self.camera, dt);
+            self.level.update_follow_camera(&mut self.camera);
+
+            if self.replay.is_some() {
+                let mut replay_input_manager = self.level_input_manager.lock().unwrap();
+                replay_input_manager.update();
+
+                if replay_input_manager.is_replay_finished() {
+                    self.show_exit_menu = true;
+                }
+            } else {
+                self.input_manager.lock().unwrap().record_tick();
+            }
+
+            match self.level.get_progress().status {
+                LevelStatus::Playing => {},
+                LevelStatus::Lost => self.save_replay_snapshot(),
+                LevelStatus::Won => self.save_replay(),
+            }
+        }
 
         if let Some(true) = self.input_manager.lock().unwrap().is_key_pressed(Keycode::Escape) {
             self.show_exit_menu = true;
@@ -73,18 +154,71 @@ impl<'a, 'b> Scene for GameScene<'a, 'b> {
     fn draw_ui(&mut self, ctx: &mut Context, ui: &Ui) -> SceneState {
         let mut next_state = NextState::Continue;
 
-        if self.show_exit_menu {
+        let mut restart = false;
+        let mut respawn = false;
+        let mut quit = false;
+
+        let progress = self.level.get_progress();
+        let score = self.level.get_score();
+
+        ui.window(im_str!("HUD")).title_bar(false).resizable(false).movable(false).always_auto_resize(true).position((10., 10.), ImGuiCond::Always).build(|| {
+            ui.text(format!("Score : {}", score));
+
+            if let Some(inventory) = self.level.get_followed_inventory() {
+                ui.text(format!("Pièces : {}", inventory.coins));
+                ui.text(format!("Clés : {}", inventory.keys.len()));
+            }
+        });
+
+        if progress.status != LevelStatus::Playing {
+            let title = if progress.status == LevelStatus::Won { im_str!("Niveau terminé !") } else { im_str!("Perdu !") };
+
+            ui.window(title).resizable(false).collapsible(false).center(ui.frame_size(), (250., 180.), ImGuiCond::Always, ImGuiCond::Always).build(|| {
+                ui.text(format!("Temps : {:.1} s", progress.elapsed_time));
+                ui.text(format!("Objets ramassés : {}", progress.collected_items));
+                ui.text(format!("Score : {}", score));
+
+                if progress.status == LevelStatus::Lost && self.replay.is_none() && self.level.can_respawn() {
+                    if ui.button(im_str!("Reprendre au point de contrôle"), (-1., 25.)) {
+                        respawn = true;
+                    }
+                }
+                if ui.button(im_str!("Recommencer"), (-1., 25.)) {
+                    restart = true;
+                }
+                if ui.button(im_str!("Quitter"), (-1., 25.)) {
+                    quit = true;
+                }
+            });
+        } else if self.show_exit_menu {
             ui.window(im_str!("Menu")).title_bar(false).resizable(false).center(ui.frame_size(), (200., 100.), ImGuiCond::Always, ImGuiCond::Always).build(|| {
                 if ui.button(im_str!("Reprendre"), (-1., 25.)) {
                     self.show_exit_menu = false;
                 }
-                if ui.button(im_str!("Recommencer"), (-1., 25.)) {}
+                if ui.button(im_str!("Recommencer"), (-1., 25.)) {
+                    restart = true;
+                }
                 if ui.button(im_str!("Quitter"), (-1., 25.)) {
-                    next_state = NextState::Replace(Box::new(MainScene::new(ctx, self.input_manager.clone())));
+                    quit = true;
                 }
             });
         }
 
+        if respawn {
+            self.level.respawn();
+            self.input_manager.lock().unwrap().record_respawn();
+        }
+
+        if restart {
+            self.save_replay();
+            self.restart(ctx);
+        }
+
+        if quit {
+            self.save_replay();
+            next_state = NextState::Replace(Box::new(MainScene::new(ctx, self.input_manager.clone())));
+        }
+
         Ok(next_state)
     }
 
@@ -1,5 +1,6 @@
 use crate::{
     ecs::level::LevelConfig,
+    game::GameConfig,
     scenes::{
         editor_scene::EditorScene,
         game_scene::GameScene,
@@ -8,9 +9,11 @@ use crate::{
         SceneState,
     },
     utils::{
+        audio,
         constants,
         imgui::ImGuiExtensions,
         input_manager::RefInputManager,
+        replay::Replay,
         resources_manager::RefRM,
     },
     wrapper::imgui_wrapper::CenteredWindow,
@@ -114,13 +117,31 @@ impl Scene for MainScene {
 
                 if ui.button(im_str!("Jouer"), (-1., 0.)) {
                     if let Some(config) = levels.iter().nth(*levels_window_select_level as usize) {
-                        result = NextState::Replace(Box::new(GameScene::new(ctx,input_manager.clone(), config.clone())));
+                        match GameScene::new(ctx, input_manager.clone(), config.clone()) {
+                            Ok(scene) => result = NextState::Replace(Box::new(scene)),
+                            Err(err) => eprintln!("Impossible de charger le niveau {} ! Erreur : {}", config.name, err)
+                        }
+                    }
+                }
+
+                if ui.button(im_str!("Regarder le replay"), (-1., 0.)) {
+                    if let Some(config) = levels.iter().nth(*levels_window_select_level as usize) {
+                        match Replay::load(&config.replay_path()) {
+                            Ok(replay) => match GameScene::new_replay(ctx, input_manager.clone(), config.clone(), replay) {
+                                Ok(scene) => result = NextState::Replace(Box::new(scene)),
+                                Err(err) => eprintln!("Impossible de charger le niveau {} ! Erreur : {}", config.name, err)
+                            },
+                            Err(err) => eprintln!("Impossible de charger le replay du niveau {} ! Erreur : {}", config.name, err)
+                        }
                     }
                 }
 
                 if ui.button(im_str!("Éditer"), (-1., 0.)) {
                     if let Some(config) = levels.iter().nth(*levels_window_select_level as usize) {
-                        result = NextState::Replace(Box::new(EditorScene::load_level(ctx,None, input_manager.clone(), config.clone())));
+                        match EditorScene::load_level(ctx, None, input_manager.clone(), config.clone()) {
+                            Ok(scene) => result = NextState::Replace(Box::new(scene)),
+                            Err(err) => eprintln!("Impossible de charger le niveau {} ! Erreur : {}", config.name, err)
+                        }
                     }
                 }
 
@@ -131,7 +152,23 @@ impl Scene for MainScene {
         }
 
         if self.show_settings_window {
-            ui.window(im_str!("Options")).opened(&mut self.show_settings_window).resizable(false).center(ui.frame_size(), (150., 200.), ImGuiCond::Always, ImGuiCond::Once).build(|| {});
+            ui.window(im_str!("Options")).opened(&mut self.show_settings_window).resizable(false).center(ui.frame_size(), (300., 150.), ImGuiCond::Always, ImGuiCond::Once).build(|| {
+                let mut volumes = audio::get_volumes();
+
+                let changed = ui.slider_float(im_str!("volume général"), &mut volumes.master, 0., 1.).build()
+                    | ui.slider_float(im_str!("musique"), &mut volumes.music, 0., 1.).build()
+                    | ui.slider_float(im_str!("effets"), &mut volumes.effects, 0., 1.).build();
+
+                if changed {
+                    audio::set_volumes(volumes);
+                }
+
+                if ui.button(im_str!("Enregistrer"), (-1., 0.)) {
+                    let mut config = GameConfig::load().unwrap_or_default();
+                    config.volumes = volumes;
+                    config.save();
+                }
+            });
         }
 
         Ok(result)
@@ -0,0 +1,102 @@
+use crate::utils::constants;
+use ggez::{
+    Context,
+    error::{
+        GameError,
+        GameResult,
+    },
+    filesystem,
+    graphics::{
+        self,
+        Image,
+    },
+};
+use image::{
+    GenericImage,
+    RgbaImage,
+};
+use std::{
+    collections::HashMap,
+    io::Read,
+    path::{
+        Path,
+        PathBuf,
+    },
+};
+
+/// Texture regroupant plusieurs petites textures, pour les dessiner dans un seul `SpriteBatch`.
+#[derive(Clone)]
+pub struct TextureAtlas {
+    image: Image,
+    /// Sous-rectangle normalisé de chaque texture dans l'atlas
+    regions: HashMap<PathBuf, graphics::Rect>,
+}
+
+impl TextureAtlas {
+    /// Range les textures par étagères dans une texture de `ATLAS_SIZE` pixels de côté.
+    /// Les textures trop grandes ou qui ne rentrent plus sont ignorées et restent dessinées séparément.
+    pub fn build<'p, I: IntoIterator<Item = &'p Path>>(ctx: &mut Context, paths: I) -> GameResult<Option<TextureAtlas>> {
+        let mut textures = Vec::new();
+
+        for path in paths {
+            let texture = Self::read_rgba(ctx, path)?;
+
+            if texture.width() <= constants::ATLAS_MAX_TEXTURE_SIZE && texture.height() <= constants::ATLAS_MAX_TEXTURE_SIZE {
+                textures.push((path.to_owned(), texture));
+            }
+        }
+
+        if textures.len() < 2 {
+            return Ok(None);
+        }
+
+        textures.sort_by(|a, b| b.1.height().cmp(&a.1.height()));
+
+        let size = constants::ATLAS_SIZE;
+        let padding = constants::ATLAS_PADDING;
+
+        let mut atlas = RgbaImage::new(size, size);
+        let mut regions = HashMap::new();
+
+        let (mut x, mut y, mut shelf_height) = (0, 0, 0);
+
+        for (path, texture) in textures {
+            let (w, h) = (texture.width(), texture.height());
+
+            if x + w > size {
+                x = 0;
+                y += shelf_height + padding;
+                shelf_height = 0;
+            }
+
+            if y + h > size {
+                continue;
+            }
+
+            atlas.copy_from(&texture, x, y);
+
+            regions.insert(path, graphics::Rect::new(x as f32 / size as f32, y as f32 / size as f32, w as f32 / size as f32, h as f32 / size as f32));
+
+            x += w + padding;
+            shelf_height = shelf_height.max(h);
+        }
+
+        let image = Image::from_rgba8(ctx, size as u16, size as u16, &atlas.into_raw())?;
+
+        Ok(Some(TextureAtlas { image, regions }))
+    }
+
+    fn read_rgba(ctx: &mut Context, path: &Path) -> GameResult<RgbaImage> {
+        let mut buf = Vec::new();
+        filesystem::open(ctx, path)?.read_to_end(&mut buf)?;
+
+        image::load_from_memory(&buf)
+            .map(|img| img.to_rgba())
+            .map_err(|err| GameError::ResourceLoadError(format!("Impossible de décoder l'image {:?} : {}", path, err)))
+    }
+
+    pub fn get_image(&self) -> &Image { &self.image }
+
+    /// Sous-rectangle normalisé de la texture dans l'atlas, `None` si elle n'y a pas été placée.
+    pub fn get_region(&self, path: &Path) -> Option<graphics::Rect> { self.regions.get(path).cloned() }
+}
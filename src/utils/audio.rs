@@ -0,0 +1,163 @@
+use crate::utils::resources_manager::ResourcesManager;
+use ggez::{
+    audio::Source,
+    Context,
+    GameResult,
+};
+use lazy_static::lazy_static;
+use serde::{
+    Deserialize,
+    Serialize,
+};
+use std::{
+    mem,
+    path::{
+        Path,
+        PathBuf,
+    },
+    sync::RwLock,
+};
+
+/// Volumes réglés dans les options, de 0 à 1.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug)]
+pub struct VolumeConfig {
+    pub master: f32,
+    pub music: f32,
+    pub effects: f32,
+}
+
+impl Default for VolumeConfig {
+    fn default() -> Self {
+        VolumeConfig { master: 1., music: 0.7, effects: 1. }
+    }
+}
+
+impl VolumeConfig {
+    pub fn music_volume(&self) -> f32 { self.master * self.music }
+
+    pub fn effects_volume(&self) -> f32 { self.master * self.effects }
+}
+
+lazy_static! {
+    /// Volumes partagés par toutes les scènes, initialisés depuis la configuration du jeu.
+    static ref VOLUMES: RwLock<VolumeConfig> = RwLock::new(VolumeConfig::default());
+}
+
+pub fn get_volumes() -> VolumeConfig { *VOLUMES.read().unwrap() }
+
+pub fn set_volumes(volumes: VolumeConfig) {
+    *VOLUMES.write().unwrap() = volumes;
+}
+
+/// Sortie audio d'un niveau.
+/// Les volumes donnés sont ceux du niveau et de l'atténuation, les volumes des options sont appliqués par le backend.
+pub trait AudioBackend {
+    fn play_sound(&mut self, path: &Path, volume: f32);
+    fn play_music(&mut self, path: &Path, looping: bool, volume: f32);
+    fn stop_music(&mut self);
+    /// Appelé à chaque tick avec le contexte, nécessaire pour charger et jouer les sons.
+    fn update(&mut self, ctx: &mut Context, resources_manager: &mut ResourcesManager);
+}
+
+/// Backend silencieux, pour exécuter un niveau sans périphérique audio (simulation, tests).
+pub struct NullAudioBackend;
+
+impl AudioBackend for NullAudioBackend {
+    fn play_sound(&mut self, _path: &Path, _volume: f32) {}
+    fn play_music(&mut self, _path: &Path, _looping: bool, _volume: f32) {}
+    fn stop_music(&mut self) {}
+    fn update(&mut self, _ctx: &mut Context, _resources_manager: &mut ResourcesManager) {}
+}
+
+enum AudioCommand {
+    Sound(PathBuf, f32),
+    Music(PathBuf, bool, f32),
+    StopMusic,
+}
+
+/// Backend utilisant le périphérique audio de ggez, les commandes sont exécutées au prochain `update`.
+#[derive(Default)]
+pub struct GgezAudioBackend {
+    commands: Vec<AudioCommand>,
+    /// Sons en cours de lecture, une source détruite s'arrête immédiatement
+    sounds: Vec<Source>,
+    /// Musique en cours et son volume dans le niveau
+    music: Option<(Source, f32)>,
+}
+
+impl GgezAudioBackend {
+    fn create_source(ctx: &mut Context, resources_manager: &mut ResourcesManager, path: &Path) -> GameResult<Option<Source>> {
+        if resources_manager.has_failed(path) {
+            return Ok(None);
+        }
+
+        let data = resources_manager.load_or_get_sound(ctx, path)?.cloned();
+
+        match data {
+            Some(data) => Ok(Some(Source::from_data(ctx, data)?)),
+            None => Ok(None),
+        }
+    }
+
+    fn stop_current_music(&mut self) {
+        if let Some((source, _)) = self.music.take() {
+            source.stop();
+        }
+    }
+
+    fn execute(&mut self, ctx: &mut Context, resources_manager: &mut ResourcesManager, command: AudioCommand, volumes: VolumeConfig) -> GameResult<()> {
+        match command {
+            AudioCommand::Sound(path, volume) => {
+                if let Some(mut source) = Self::create_source(ctx, resources_manager, &path)? {
+                    source.set_volume(volume * volumes.effects_volume());
+                    source.play()?;
+                    self.sounds.push(source);
+                }
+            }
+            AudioCommand::Music(path, looping, volume) => {
+                self.stop_current_music();
+
+                if let Some(mut source) = Self::create_source(ctx, resources_manager, &path)? {
+                    source.set_repeat(looping);
+                    source.set_volume(volume * volumes.music_volume());
+                    source.play()?;
+                    self.music = Some((source, volume));
+                }
+            }
+            AudioCommand::StopMusic => self.stop_current_music(),
+        }
+
+        Ok(())
+    }
+}
+
+impl AudioBackend for GgezAudioBackend {
+    fn play_sound(&mut self, path: &Path, volume: f32) {
+        self.commands.push(AudioCommand::Sound(path.to_owned(), volume));
+    }
+
+    fn play_music(&mut self, path: &Path, looping: bool, volume: f32) {
+        self.commands.push(AudioCommand::Music(path.to_owned(), looping, volume));
+    }
+
+    fn stop_music(&mut self) {
+        self.commands.push(AudioCommand::StopMusic);
+    }
+
+    fn update(&mut self, ctx: &mut Context, resources_manager: &mut ResourcesManager) {
+        let volumes = get_volumes();
+
+        for command in mem::replace(&mut self.commands, vec![]) {
+            if let Err(err) = self.execute(ctx, resources_manager, command, volumes) {
+                eprintln!("Impossible de jouer le son : {}", err);
+            }
+        }
+
+        self.sounds.retain(|source| source.playing());
+
+        // Les volumes peuvent être modifiés pendant la lecture
+        if let Some((ref mut source, volume)) = self.music {
+            source.set_volume(volume * volumes.music_volume());
+        }
+    }
+}
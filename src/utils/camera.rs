@@ -87,6 +87,13 @@ impl Camera {
         Point2::new(point.x / scale_x - self.position.x, point.y / scale_y - self.position.y)
     }
 
+    /// Position à l'écran d'un point d'un calque qui défile `parallax` fois plus vite que la caméra.
+    pub fn world_point_to_screen_parallax(&self, point: Point2<f64>, parallax: f64) -> Point2<f64> {
+        let (scale_x, scale_y) = self.get_scale();
+
+        Point2::new(point.x / scale_x - self.position.x * parallax, point.y / scale_y - self.position.y * parallax)
+    }
+
     pub fn world_size_to_screen(&self, size: Vector2<f64>) -> Vector2<f64> {
         let (scale_x, scale_y) = self.get_scale();
 
@@ -1,6 +1,17 @@
 pub const DESIRED_FPS: u32 = 144;
 pub const CHUNK_SIZE: u32 = 500;
 pub const PHYSICS_EPSILON: f64 = 0.2;
+pub const GRAVITY: f64 = 2000.;
+pub const PHYSICS_DROP_THROUGH_TICKS: u32 = 10;
+pub const SCRIPT_HOT_RELOAD_TICKS: u32 = 60;
+pub const SCRIPT_CONSOLE_MAX_LINES: usize = 100;
+pub const ATLAS_SIZE: u32 = 2048;
+pub const ATLAS_MAX_TEXTURE_SIZE: u32 = 256;
+pub const ATLAS_PADDING: u32 = 1;
+pub const LIGHT_TEXTURE_SIZE: u32 = 256;
+pub const PARTICLE_TEXTURE_SIZE: u32 = 32;
+pub const AUDIO_FULL_VOLUME_DISTANCE: f64 = 800.;
+pub const AUDIO_MAX_DISTANCE: f64 = 2500.;
 pub const CAMERA_VIEW_SIZE: (u32, u32) = (1920, 1080);
 
 pub const EDITOR_CAMERA_MOVE_SPEED: f64 = 1000.;
@@ -19,6 +30,8 @@ pub mod path {
         pub static ref LEVELS_DIR: PathBuf = RESSOURCES_DIR.join("levels");
         pub static ref LEVEL_CONFIG_FILE: PathBuf = Path::new("level.ron").to_owned();
         pub static ref LEVEL_WORLD_DATA_FILE: PathBuf = Path::new("world.dat").to_owned();
+        pub static ref LEVEL_WORLD_BINARY_FILE: PathBuf = Path::new("world.bin").to_owned();
+        pub static ref LEVEL_REPLAY_FILE: PathBuf = Path::new("replay.ron").to_owned();
 
         pub static ref MAIN_MENU_BACKGROUND_FILE: PathBuf = Path::new("/game/mainmenu.png").to_owned();
         pub static ref MAIN_MENU_LOGO_FILE: PathBuf = Path::new("/game/logo.png").to_owned();
@@ -2,6 +2,7 @@ use ggez::event::{
     Keycode,
     MouseButton,
 };
+use crate::utils::replay::Replay;
 use nalgebra::Point2;
 use std::{
     collections::HashMap,
@@ -24,6 +25,8 @@ pub struct InputManager {
     pressed_mouse: HashMap<MouseButton, JustPressed>,
     mouse_pos: Point2<i32>,
     last_mouse_pos: Point2<i32>,
+    recording: Option<Replay>,
+    playback: Option<(Replay, usize)>,
 }
 
 impl Default for InputManager {
@@ -39,6 +42,64 @@ impl InputManager {
             pressed_mouse: HashMap::new(),
             mouse_pos: Point2::new(0, 0),
             last_mouse_pos: Point2::new(0, 0),
+            recording: None,
+            playback: None,
+        }
+    }
+
+    /// Gestionnaire dont les touches sont lues depuis le replay, un tick par appel à `update`.
+    pub fn from_replay(replay: Replay) -> Self {
+        let mut input_manager = InputManager::new();
+        input_manager.playback = Some((replay, 0));
+        input_manager.load_replay_tick();
+        input_manager
+    }
+
+    pub fn start_recording(&mut self) {
+        self.recording = Some(Replay::default());
+    }
+
+    pub fn stop_recording(&mut self) -> Option<Replay> {
+        self.recording.take()
+    }
+
+    pub fn get_recording(&self) -> Option<&Replay> {
+        self.recording.as_ref()
+    }
+
+    /// Enregistre les touches du tick qui vient d'être joué, à appeler après chaque mise à jour du niveau.
+    pub fn record_tick(&mut self) {
+        if let Some(ref mut replay) = self.recording {
+            replay.push_tick(self.pressed_keys.iter().map(|(key, jp)| (*key as i32, *jp)).collect());
+        }
+    }
+
+    pub fn record_respawn(&mut self) {
+        if let Some(ref mut replay) = self.recording {
+            replay.push_respawn();
+        }
+    }
+
+    /// Vrai si le joueur a repris la partie au point de contrôle avant le prochain tick du replay.
+    pub fn is_replay_respawn(&self) -> bool {
+        match self.playback {
+            Some((ref replay, tick)) => replay.has_respawn(tick),
+            None => false
+        }
+    }
+
+    pub fn is_replay_finished(&self) -> bool {
+        match self.playback {
+            Some((ref replay, tick)) => tick >= replay.len(),
+            None => false
+        }
+    }
+
+    fn load_replay_tick(&mut self) {
+        if let Some((ref replay, tick)) = self.playback {
+            self.pressed_keys = replay.get_tick(tick).map_or(HashMap::new(), |keys| {
+                keys.iter().filter_map(|(key, jp)| Keycode::from_i32(*key).map(|key| (key, *jp))).collect()
+            });
         }
     }
 
@@ -54,13 +115,22 @@ impl InputManager {
         self.pressed_keys.get(&key).cloned()
     }
 
+    pub fn get_pressed_keys(&self) -> Vec<(Keycode, JustPressed)> {
+        self.pressed_keys.iter().map(|(key, jp)| (*key, *jp)).collect()
+    }
+
     pub fn is_mouse_pressed(&self, button: MouseButton) -> Option<JustPressed> {
         self.pressed_mouse.get(&button).cloned()
     }
 
     pub fn update(&mut self) {
-        for (_, just_pressed) in self.pressed_keys.iter_mut() {
-            *just_pressed = false;
+        if let Some((_, ref mut tick)) = self.playback {
+            *tick += 1;
+            self.load_replay_tick();
+        } else {
+            for (_, just_pressed) in self.pressed_keys.iter_mut() {
+                *just_pressed = false;
+            }
         }
 
         for (_, just_pressed) in self.pressed_mouse.iter_mut() {
@@ -1,8 +1,11 @@
 pub mod input_manager;
 pub mod resources_manager;
+pub mod atlas;
 pub mod constants;
 pub mod math;
 pub mod imgui;
 pub mod serde;
 pub mod camera;
 pub mod ggez;
+pub mod replay;
+pub mod audio;
@@ -0,0 +1,54 @@
+use crate::utils::input_manager::JustPressed;
+use ron;
+use serde::{
+    Deserialize,
+    Serialize,
+};
+use std::{
+    fs::File,
+    io::Write,
+    path::Path,
+};
+
+/// Touches enfoncées (code de la touche, juste pressée) à chaque tick de mise à jour.
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct Replay {
+    ticks: Vec<Vec<(i32, JustPressed)>>,
+    /// Ticks avant lesquels le joueur a repris la partie au dernier point de contrôle
+    #[serde(default)]
+    respawns: Vec<usize>,
+}
+
+impl Replay {
+    pub fn load(path: &Path) -> Result<Self, ron::de::Error> {
+        let replay_file = File::open(path).map_err(|err| ron::de::Error::from(err))?;
+        ron::de::from_reader::<File, Self>(replay_file)
+    }
+
+    pub fn save(&self, path: &Path) {
+        let mut replay_file = File::create(path).expect("Impossible de créer le fichier de replay !");
+        let content = ron::ser::to_string(&self).expect("Impossible de sérialiser le replay !");
+        replay_file.write_all(content.as_bytes()).expect("Impossible d'écrire le replay dans le fichier !");
+    }
+
+    pub fn push_tick(&mut self, keys: Vec<(i32, JustPressed)>) {
+        self.ticks.push(keys);
+    }
+
+    pub fn get_tick(&self, tick: usize) -> Option<&Vec<(i32, JustPressed)>> {
+        self.ticks.get(tick)
+    }
+
+    /// Enregistre une reprise au point de contrôle avant le prochain tick.
+    pub fn push_respawn(&mut self) {
+        self.respawns.push(self.ticks.len());
+    }
+
+    pub fn has_respawn(&self, tick: usize) -> bool {
+        self.respawns.contains(&tick)
+    }
+
+    pub fn len(&self) -> usize {
+        self.ticks.len()
+    }
+}
@@ -1,11 +1,16 @@
+use crate::utils::atlas::TextureAtlas;
 use ggez::{
+    audio::SoundData,
     Context,
     error::GameError,
     graphics::Image,
 };
 use std::{
     cell::RefCell,
-    collections::HashMap,
+    collections::{
+        HashMap,
+        HashSet,
+    },
     path::{
         Path,
         PathBuf,
@@ -17,7 +22,13 @@ pub type RefRM = Rc<RefCell<ResourcesManager>>;
 
 #[derive(Clone)]
 pub struct ResourcesManager {
-    textures: HashMap<PathBuf, Image>
+    textures: HashMap<PathBuf, Image>,
+    sounds: HashMap<PathBuf, SoundData>,
+    /// Textures et sons qui n'ont pas pu être chargées, pour ne pas réessayer à chaque image
+    failed: HashSet<PathBuf>,
+    atlas: Option<TextureAtlas>,
+    /// Texture des particules sans texture, générée au premier chargement
+    particle_texture: Option<Image>,
 }
 
 unsafe impl Send for ResourcesManager {}
@@ -27,13 +38,20 @@ unsafe impl Sync for ResourcesManager {}
 impl ResourcesManager {
     pub fn load_texture(&mut self, ctx: &mut Context, path: &Path) -> Result<(), GameError> {
         if !self.textures.contains_key(path) {
-            self.textures.insert(path.to_owned(), Image::new(ctx, path)?);
+            let image = Image::new(ctx, path).map_err(|err| {
+                self.failed.insert(path.to_owned());
+                err
+            })?;
+
+            self.textures.insert(path.to_owned(), image);
         }
 
         Ok(())
     }
 
-    pub fn get_texture(&mut self, path: &Path) -> Option<&Image> {
+    pub fn has_failed(&self, path: &Path) -> bool { self.failed.contains(path) }
+
+    pub fn get_texture(&self, path: &Path) -> Option<&Image> {
         self.textures.get(path).map_or(None, |tex| Some(&tex))
     }
 
@@ -41,10 +59,51 @@ impl ResourcesManager {
         self.load_texture(ctx, path)?;
         Ok(self.get_texture(path))
     }
+
+    /// Regroupe les petites textures chargées dans un atlas, les textures chargées ensuite n'y sont pas ajoutées.
+    pub fn build_atlas(&mut self, ctx: &mut Context) {
+        self.atlas = match TextureAtlas::build(ctx, self.textures.keys().map(|path| path.as_path())) {
+            Ok(atlas) => atlas,
+            Err(err) => {
+                eprintln!("Impossible de créer l'atlas de textures : {}", err);
+                None
+            }
+        };
+    }
+
+    pub fn load_sound(&mut self, ctx: &mut Context, path: &Path) -> Result<(), GameError> {
+        if !self.sounds.contains_key(path) {
+            let sound = SoundData::new(ctx, path).map_err(|err| {
+                self.failed.insert(path.to_owned());
+                err
+            })?;
+
+            self.sounds.insert(path.to_owned(), sound);
+        }
+
+        Ok(())
+    }
+
+    pub fn get_sound(&self, path: &Path) -> Option<&SoundData> {
+        self.sounds.get(path)
+    }
+
+    pub fn load_or_get_sound(&mut self, ctx: &mut Context, path: &Path) -> Result<Option<&SoundData>, GameError> {
+        self.load_sound(ctx, path)?;
+        Ok(self.get_sound(path))
+    }
+
+    pub fn get_atlas(&self) -> Option<&TextureAtlas> { self.atlas.as_ref() }
+
+    pub fn get_particle_texture(&self) -> Option<&Image> { self.particle_texture.as_ref() }
+
+    pub fn set_particle_texture(&mut self, image: Image) {
+        self.particle_texture = Some(image);
+    }
 }
 
 impl Default for ResourcesManager {
     fn default() -> Self {
-        ResourcesManager { textures: HashMap::new() }
+        ResourcesManager { textures: HashMap::new(), sounds: HashMap::new(), failed: HashSet::new(), atlas: None, particle_texture: None }
     }
 }